@@ -0,0 +1,253 @@
+use std::fmt::Display;
+
+use transip::acme::{Challenge, Propagation, nameserver_addresses};
+use transip::api::dns::DnsApi;
+use transip::api::domain::{Domain, DomainApi};
+use transip::{Error, Result};
+
+const ACME_CHALLENGE_PREFIX: &str = "_acme-challenge.";
+const CERTBOT_DOMAIN: &str = "CERTBOT_DOMAIN";
+const CERTBOT_VALIDATION: &str = "CERTBOT_VALIDATION";
+
+pub const USAGE: &str = "\
+usage: transip-acme-hook auth | cleanup
+         with CERTBOT_DOMAIN and CERTBOT_VALIDATION set, as certbot manual hook
+       transip-acme-hook present | cleanup <fqdn> <value>
+         as lego exec provider";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Present,
+    Cleanup,
+}
+
+/// A single call of the hook by certbot or lego
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hook {
+    pub action: Action,
+    /// Name in the certificate, like `www.example.com`
+    pub identifier: String,
+    pub value: String,
+}
+
+impl Hook {
+    /// Reads the arguments, without the program name, and the environment.
+    ///
+    /// Certbot calls the hook with `auth` or `cleanup` and passes the challenge
+    /// in `CERTBOT_DOMAIN` and `CERTBOT_VALIDATION`.
+    /// Lego calls the hook with `present` or `cleanup`, the fqdn of the challenge, like
+    /// `_acme-challenge.www.example.com.`, and the value.
+    pub fn parse<F>(args: &[String], env: F) -> std::result::Result<Self, String>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+        let certbot = |action| -> std::result::Result<Self, String> {
+            let variable = |name| env(name).ok_or_else(|| format!("{name} not set"));
+            Ok(Self {
+                action,
+                identifier: variable(CERTBOT_DOMAIN)?,
+                value: variable(CERTBOT_VALIDATION)?,
+            })
+        };
+        let lego = |action, fqdn: &str, value: &str| {
+            fqdn.trim_end_matches('.')
+                .strip_prefix(ACME_CHALLENGE_PREFIX)
+                .map(|identifier| Self {
+                    action,
+                    identifier: identifier.to_owned(),
+                    value: value.to_owned(),
+                })
+                .ok_or_else(|| format!("{fqdn} does not start with {ACME_CHALLENGE_PREFIX}"))
+        };
+        match args.as_slice() {
+            ["auth"] => certbot(Action::Present),
+            ["cleanup"] => certbot(Action::Cleanup),
+            ["present", fqdn, value] => lego(Action::Present, fqdn, value),
+            ["cleanup", fqdn, value] => lego(Action::Cleanup, fqdn, value),
+            _ => Err(USAGE.to_owned()),
+        }
+    }
+
+    /// Publishes the challenge and waits for the nameservers if `propagation` is given,
+    /// or removes the challenge
+    pub fn run<A>(&self, api: &A, propagation: Option<&Propagation>) -> Result<()>
+    where
+        A: DnsApi + DomainApi,
+    {
+        let domains = api.domain_list()?;
+        let domain = zone(&domains, &self.identifier).ok_or_else(|| {
+            Error::AcmeIdentifier(format!("No domain found for {}", self.identifier))
+        })?;
+        let challenge = Challenge::new(&domain.name, &self.identifier, &[&self.value])?;
+        match self.action {
+            Action::Present => {
+                challenge.publish(api)?;
+                match propagation {
+                    Some(propagation) => {
+                        challenge.wait(&nameserver_addresses(domain)?, propagation)
+                    }
+                    None => Ok(()),
+                }
+            }
+            Action::Cleanup => challenge.cleanup(api),
+        }
+    }
+}
+
+impl Display for Hook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.action {
+            Action::Present => write!(f, "present challenge for {}", self.identifier),
+            Action::Cleanup => write!(f, "cleanup challenge for {}", self.identifier),
+        }
+    }
+}
+
+/// The domain of the account that `name` belongs to, the longest match if domains are nested
+pub fn zone<'a>(domains: &'a [Domain], name: &str) -> Option<&'a Domain> {
+    let name = name.trim_end_matches('.').to_lowercase();
+    let name = name.strip_prefix("*.").unwrap_or(&name);
+    domains
+        .iter()
+        .filter(|domain| {
+            let domain_name = domain.name.to_lowercase();
+            name == domain_name
+                || name
+                    .strip_suffix(&domain_name)
+                    .is_some_and(|subdomain| subdomain.ends_with('.'))
+        })
+        .max_by_key(|domain| domain.name.len())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::fs::File;
+
+    use transip::api::dns::DnsEntry;
+    use transip::api::domain::DomainApi;
+    use transip::{Client, Error, MemoryTokenStore};
+    use transip_fake::FakeTransip;
+
+    use super::{Action, Hook, zone};
+
+    const KEY: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../transip-fake/fixtures/test-key.pem"
+    );
+    const LOGIN: &str = "fake";
+
+    fn fake() -> FakeTransip {
+        FakeTransip::new()
+            .with_account_key(LOGIN, File::open(KEY).unwrap())
+            .unwrap()
+            .with_domain("example.com")
+            .with_domain("sub.example.com")
+            .with_domain("example.org")
+    }
+
+    /// A client of the fake, without a token file
+    fn client(fake: FakeTransip) -> Client {
+        Client::builder()
+            .user_name(LOGIN)
+            .private_key_file(KEY)
+            .token_store(MemoryTokenStore::new())
+            .transport(fake)
+            .build()
+            .unwrap()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_certbot() {
+        let env = HashMap::from([
+            ("CERTBOT_DOMAIN", "www.example.com"),
+            ("CERTBOT_VALIDATION", "validation"),
+        ]);
+        let env = |name: &str| env.get(name).map(|value| value.to_string());
+        let hook = Hook::parse(&args(&["auth"]), env).unwrap();
+        assert_eq!(
+            hook,
+            Hook {
+                action: Action::Present,
+                identifier: "www.example.com".to_owned(),
+                value: "validation".to_owned(),
+            }
+        );
+        let hook = Hook::parse(&args(&["cleanup"]), env).unwrap();
+        assert_eq!(hook.action, Action::Cleanup);
+        assert!(Hook::parse(&args(&["auth"]), |_| None).is_err());
+    }
+
+    #[test]
+    fn parse_lego() {
+        let present = args(&["present", "_acme-challenge.www.example.com.", "validation"]);
+        let hook = Hook::parse(&present, |_| None).unwrap();
+        assert_eq!(hook.action, Action::Present);
+        assert_eq!(hook.identifier, "www.example.com");
+        assert_eq!(hook.value, "validation");
+        let cleanup = args(&["cleanup", "www.example.com.", "validation"]);
+        assert!(Hook::parse(&cleanup, |_| None).is_err());
+        assert!(Hook::parse(&args(&["timeout"]), |_| None).is_err());
+    }
+
+    #[test]
+    fn zone_of_name() {
+        let client = client(fake());
+        let domains = client.domain_list().unwrap();
+        let name = |name| zone(&domains, name).map(|domain| domain.name.as_str());
+        assert_eq!(name("example.com"), Some("example.com"));
+        assert_eq!(name("*.www.Example.com."), Some("example.com"));
+        assert_eq!(name("www.sub.example.com"), Some("sub.example.com"));
+        assert_eq!(name("notexample.com"), None);
+        assert_eq!(name("example.net"), None);
+    }
+
+    #[test]
+    fn present_and_cleanup() {
+        let other = "_acme-challenge.www 60 TXT other"
+            .parse::<DnsEntry>()
+            .unwrap();
+        let fake = fake().with_dns_entry("sub.example.com", other.clone());
+        let client = client(fake.clone());
+        let hook = Hook {
+            action: Action::Present,
+            identifier: "www.sub.example.com".to_owned(),
+            value: "validation".to_owned(),
+        };
+        hook.run(&client, None).unwrap();
+        let published = "_acme-challenge.www 60 TXT validation"
+            .parse::<DnsEntry>()
+            .unwrap();
+        assert_eq!(
+            fake.dns_entries("sub.example.com"),
+            vec![other.clone(), published]
+        );
+        assert!(fake.dns_entries("example.com").is_empty());
+
+        let hook = Hook {
+            action: Action::Cleanup,
+            ..hook
+        };
+        hook.run(&client, None).unwrap();
+        assert_eq!(fake.dns_entries("sub.example.com"), vec![other]);
+    }
+
+    #[test]
+    fn unknown_domain() {
+        let client = client(fake());
+        let hook = Hook {
+            action: Action::Present,
+            identifier: "example.net".to_owned(),
+            value: "validation".to_owned(),
+        };
+        assert!(matches!(
+            hook.run(&client, None),
+            Err(Error::AcmeIdentifier(_))
+        ));
+    }
+}
@@ -0,0 +1,67 @@
+#![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"))]
+
+use std::process::ExitCode;
+use std::time::Duration;
+
+use tracing_subscriber::EnvFilter;
+use transip::acme::Propagation;
+use transip::{Client, configuration_from_profile};
+
+use hook::Hook;
+
+mod hook;
+
+const TRANSIP_ACME_PROPAGATION_TIMEOUT: &str = "TRANSIP_ACME_PROPAGATION_TIMEOUT";
+const TRANSIP_ACME_POLLING_INTERVAL: &str = "TRANSIP_ACME_POLLING_INTERVAL";
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn seconds(name: &str) -> Result<Option<Duration>, String> {
+    env(name)
+        .map(|value| {
+            value
+                .parse()
+                .map(Duration::from_secs)
+                .map_err(|_| format!("{name} must be a number of seconds, not {value}"))
+        })
+        .transpose()
+}
+
+/// Waiting for the nameservers is skipped when the timeout is zero
+fn propagation() -> Result<Option<Propagation>, String> {
+    let default = Propagation::default();
+    let timeout = seconds(TRANSIP_ACME_PROPAGATION_TIMEOUT)?.unwrap_or(default.timeout);
+    let interval = seconds(TRANSIP_ACME_POLLING_INTERVAL)?.unwrap_or(default.interval);
+    Ok((!timeout.is_zero()).then_some(Propagation {
+        timeout,
+        interval,
+        ..default
+    }))
+}
+
+fn run() -> Result<(), String> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let hook = Hook::parse(&args, env)?;
+    let propagation = propagation()?;
+    let client = configuration_from_profile(None)
+        .and_then(Client::try_from)
+        .map_err(|error| error.to_string())?;
+    hook.run(&client, propagation.as_ref())
+        .map_err(|error| format!("{hook} failed: {error}"))
+}
+
+fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
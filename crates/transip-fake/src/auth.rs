@@ -0,0 +1,195 @@
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use chrono::Utc;
+use ring::{
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+    signature,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::ApiError;
+
+const ISSUER: &str = "api.transip.nl";
+const HEADER: &str = r#"{"typ":"JWT","alg":"HS256"}"#;
+const MAX_EXPIRATION_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// Body of a token request, as sent by the client
+#[derive(Deserialize)]
+pub(crate) struct AuthRequest {
+    pub login: String,
+    pub nonce: String,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub expiration_time: Option<String>,
+    #[serde(default = "global_key_default")]
+    pub global_key: bool,
+}
+
+fn global_key_default() -> bool {
+    true
+}
+
+/// Claims of the issued tokens, with the same names the Transip api uses
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Claims {
+    pub iss: String,
+    pub aud: String,
+    pub jti: String,
+    pub iat: i64,
+    pub nbf: i64,
+    pub exp: i64,
+    pub cid: i64,
+    pub ro: bool,
+    pub gk: bool,
+    pub kv: bool,
+}
+
+/// Signs and verifies the bearer tokens handed out by the fake
+pub(crate) struct Issuer {
+    key: hmac::Key,
+    rng: SystemRandom,
+}
+
+impl Default for Issuer {
+    fn default() -> Self {
+        let rng = SystemRandom::new();
+        let key = hmac::Key::generate(hmac::HMAC_SHA256, &rng).unwrap();
+        Self { key, rng }
+    }
+}
+
+impl Issuer {
+    pub fn issue(&self, customer_id: i64, request: &AuthRequest) -> Result<String, ApiError> {
+        let lifetime = request
+            .expiration_time
+            .as_deref()
+            .map(expiration_seconds)
+            .transpose()?
+            .unwrap_or(30 * 60);
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            iss: ISSUER.to_owned(),
+            aud: ISSUER.to_owned(),
+            jti: self.jti(),
+            iat: now,
+            nbf: now,
+            exp: now + lifetime,
+            cid: customer_id,
+            ro: request.read_only,
+            gk: request.global_key,
+            kv: true,
+        };
+        let payload = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(HEADER),
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap()),
+        );
+        let tag = hmac::sign(&self.key, payload.as_bytes());
+        Ok(format!(
+            "{payload}.{}",
+            URL_SAFE_NO_PAD.encode(tag.as_ref())
+        ))
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, ApiError> {
+        let invalid = || ApiError::unauthorized("Your access token is invalid.");
+        let (payload, tag) = token.rsplit_once('.').ok_or_else(invalid)?;
+        let tag = URL_SAFE_NO_PAD.decode(tag).map_err(|_| invalid())?;
+        hmac::verify(&self.key, payload.as_bytes(), &tag).map_err(|_| invalid())?;
+        let claims = payload
+            .split_once('.')
+            .and_then(|(_, claims)| URL_SAFE_NO_PAD.decode(claims).ok())
+            .and_then(|claims| serde_json::from_slice::<Claims>(&claims).ok())
+            .ok_or_else(invalid)?;
+        if claims.exp <= Utc::now().timestamp() {
+            Err(ApiError::unauthorized("Your access token has expired."))
+        } else {
+            Ok(claims)
+        }
+    }
+
+    fn jti(&self) -> String {
+        let mut bytes = [0u8; 12];
+        self.rng.fill(&mut bytes).unwrap();
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+}
+
+/// Checks the base64 encoded `Signature` header against the public key of the account
+pub(crate) fn verify_signature(
+    public_key: &[u8],
+    body: &[u8],
+    signature: &str,
+) -> Result<(), ApiError> {
+    let invalid = || ApiError::unauthorized("Provided signature is not valid");
+    let signature = STANDARD.decode(signature.trim()).map_err(|_| invalid())?;
+    signature::UnparsedPublicKey::new(&signature::RSA_PKCS1_2048_8192_SHA512, public_key)
+        .verify(body, &signature)
+        .map_err(|_| invalid())
+}
+
+/// Parses an expiration like `30 minutes` into seconds
+fn expiration_seconds(expiration: &str) -> Result<i64, ApiError> {
+    let invalid = || ApiError::bad_request(format!("Invalid expiration_time '{expiration}'"));
+    let (count, unit) = expiration.trim().split_once(' ').ok_or_else(invalid)?;
+    let count = count.parse::<i64>().map_err(|_| invalid())?;
+    let unit = match unit.trim().trim_end_matches('s') {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 60 * 60,
+        "day" => 24 * 60 * 60,
+        "week" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    let seconds = count * unit;
+    if seconds > 0 && seconds <= MAX_EXPIRATION_SECONDS {
+        Ok(seconds)
+    } else {
+        Err(invalid())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AuthRequest, Issuer, expiration_seconds};
+
+    fn auth_request() -> AuthRequest {
+        AuthRequest {
+            login: "fake".to_owned(),
+            nonce: "1".to_owned(),
+            read_only: true,
+            expiration_time: Some("5 minutes".to_owned()),
+            global_key: false,
+        }
+    }
+
+    #[test]
+    fn issue_and_verify() {
+        let issuer = Issuer::default();
+        let token = issuer.issue(42, &auth_request()).unwrap();
+        let claims = issuer.verify(&token).unwrap();
+        assert_eq!(claims.cid, 42);
+        assert!(claims.ro);
+        assert!(!claims.gk);
+        assert_eq!(claims.exp - claims.iat, 300);
+    }
+
+    #[test]
+    fn reject_foreign_token() {
+        let token = Issuer::default().issue(42, &auth_request()).unwrap();
+        assert_eq!(Issuer::default().verify(&token).unwrap_err().status, 401);
+    }
+
+    #[test]
+    fn expiration() {
+        assert_eq!(expiration_seconds("1 second").unwrap(), 1);
+        assert_eq!(expiration_seconds("30 minutes").unwrap(), 1800);
+        assert_eq!(expiration_seconds("2 hours").unwrap(), 7200);
+        assert!(expiration_seconds("1 fortnight").is_err());
+        assert!(expiration_seconds("ten minutes").is_err());
+    }
+}
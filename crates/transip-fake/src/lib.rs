@@ -0,0 +1,458 @@
+#![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"))]
+
+use std::io::{BufReader, Read};
+use std::sync::{Arc, Mutex};
+
+use ring::signature::{KeyPair, RsaKeyPair};
+use serde_json::json;
+use transip::api::dns::DnsEntry;
+use transip::transport::{Request, Response, Transport};
+
+pub use server::FakeServer;
+
+mod auth;
+mod routes;
+mod server;
+mod state;
+
+use state::{Account, DomainState, State};
+
+/// Path prefix of all api endpoints
+const PREFIX: &str = "/v6";
+
+/// In-memory stand-in for the Transip api.
+///
+/// Keeps domains, dns entries, mailboxes, mail forwards and vpss,
+/// and only accepts requests with a bearer token it issued itself
+/// after a correctly signed `/auth` request.
+///
+/// The fake can be used directly as [`Transport`] of a [`Client`](transip::Client),
+/// or served over http with [`FakeTransip::serve`].
+/// Clones share the same state.
+#[derive(Clone, Default)]
+pub struct FakeTransip {
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeTransip {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an account with the RSA public key, in PKCS#1 DER format, used to verify token requests
+    pub fn with_account<S, K>(self, login: S, public_key: K) -> Self
+    where
+        S: Into<String>,
+        K: Into<Vec<u8>>,
+    {
+        {
+            let mut state = self.lock();
+            let customer_id = 1000 + state.accounts.len() as i64;
+            state.accounts.insert(
+                login.into(),
+                Account {
+                    public_key: public_key.into(),
+                    customer_id,
+                },
+            );
+        }
+        self
+    }
+
+    /// Register an account using the PKCS#8 PEM private key the client signs with.
+    /// Only the public part of the key is kept.
+    pub fn with_account_key<S, R>(self, login: S, private_key_pem: R) -> std::io::Result<Self>
+    where
+        S: Into<String>,
+        R: Read,
+    {
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let key = rustls_pemfile::pkcs8_private_keys(&mut BufReader::new(private_key_pem))
+            .next()
+            .ok_or_else(|| invalid("No PKCS#8 private key found"))??;
+        let key_pair = RsaKeyPair::from_pkcs8(key.secret_pkcs8_der())
+            .map_err(|error| invalid(&error.to_string()))?;
+        Ok(self.with_account(login, key_pair.public_key().as_ref()))
+    }
+
+    pub fn with_domain(self, name: &str) -> Self {
+        self.lock()
+            .domains
+            .entry(name.to_owned())
+            .or_insert_with(|| DomainState::new(name));
+        self
+    }
+
+    /// Add a dns entry, registering the domain if needed
+    pub fn with_dns_entry(self, domain_name: &str, entry: DnsEntry) -> Self {
+        self.lock()
+            .domains
+            .entry(domain_name.to_owned())
+            .or_insert_with(|| DomainState::new(domain_name))
+            .dns_entries
+            .push(entry);
+        self
+    }
+
+    pub fn with_vps(self, name: &str) -> Self {
+        {
+            let mut state = self.lock();
+            let number = state.vpss.len() + 1;
+            state.vpss.push(state::new_vps(name, number));
+        }
+        self
+    }
+
+    /// Current dns entries of the domain
+    pub fn dns_entries(&self, domain_name: &str) -> Vec<DnsEntry> {
+        self.lock()
+            .domains
+            .get(domain_name)
+            .map(|domain| domain.dns_entries.clone())
+            .unwrap_or_default()
+    }
+
+    /// Serve the fake over http on a random port of the loopback interface
+    pub fn serve(&self) -> std::io::Result<FakeServer> {
+        FakeServer::start(self.clone())
+    }
+
+    /// Handle a single request and return the response the api would give
+    pub fn handle(&self, request: &Request) -> Response {
+        routes::handle(&mut self.lock(), request)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Transport for FakeTransip {
+    fn send(&self, request: Request) -> transip::Result<Response> {
+        Ok(self.handle(&request))
+    }
+}
+
+/// Error response of the api
+#[derive(Debug)]
+pub(crate) struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new<S: Into<String>>(status: u16, message: S) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request<S: Into<String>>(message: S) -> Self {
+        Self::new(400, message)
+    }
+
+    pub fn unauthorized<S: Into<String>>(message: S) -> Self {
+        Self::new(401, message)
+    }
+
+    pub fn not_found<S: Into<String>>(message: S) -> Self {
+        Self::new(404, message)
+    }
+}
+
+impl From<ApiError> for Response {
+    fn from(error: ApiError) -> Self {
+        Response::json(error.status, json!({ "error": error.message }).to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+
+    use transip::api::{
+        dns::{DnsApi, DnsEntry, Snapshot},
+        domain::DomainApi,
+        email::EmailApi,
+        general::GeneralApi,
+        vps::VpsApi,
+    };
+    use transip::transport::{Method, Request, Transport, UreqTransport};
+    use transip::{Client, Configuration, TokenExpiration};
+
+    use super::FakeTransip;
+
+    const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/test-key.pem");
+    const LOGIN: &str = "fake";
+    const DOMAIN: &str = "example.com";
+
+    struct TestConfiguration {
+        token_path: String,
+        read_only: bool,
+    }
+
+    impl Configuration for TestConfiguration {
+        fn user_name(&self) -> &str {
+            LOGIN
+        }
+
+        fn private_key_pem_file(&self) -> &str {
+            KEY
+        }
+
+        fn token_path(&self) -> &str {
+            self.token_path.as_str()
+        }
+
+        fn whitelisted_only(&self) -> bool {
+            false
+        }
+
+        fn read_only(&self) -> bool {
+            self.read_only
+        }
+
+        fn ipv6_only(&self) -> bool {
+            false
+        }
+
+        fn token_expiration(&self) -> TokenExpiration {
+            TokenExpiration::default()
+        }
+    }
+
+    fn fake() -> FakeTransip {
+        FakeTransip::new()
+            .with_account_key(LOGIN, File::open(KEY).unwrap())
+            .unwrap()
+            .with_domain(DOMAIN)
+            .with_vps("example-vps")
+    }
+
+    /// Runs the test with a client that has no persisted token.
+    /// The small page size makes list calls span multiple pages.
+    fn with_client<T, F>(name: &str, read_only: bool, transport: T, f: F)
+    where
+        T: Transport + 'static,
+        F: FnOnce(&Client),
+    {
+        let token_path = std::env::temp_dir()
+            .join(format!("transip-fake-{name}-{}.token", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let _ = std::fs::remove_file(&token_path);
+        let configuration = TestConfiguration {
+            token_path: token_path.clone(),
+            read_only,
+        };
+        let client = Client::try_with_transport(Box::new(configuration), transport)
+            .unwrap()
+            .with_retry_policy(transip::RetryPolicy::none())
+            .with_page_size(2);
+        f(&client);
+        drop(client);
+        let _ = std::fs::remove_file(&token_path);
+    }
+
+    /// Sends the requests to the fake server instead of the real api
+    struct Redirect {
+        url: String,
+        inner: UreqTransport,
+    }
+
+    impl Transport for Redirect {
+        fn send(&self, mut request: Request) -> transip::Result<transip::transport::Response> {
+            request.url = request.url.replace("https://api.transip.nl/v6", &self.url);
+            self.inner.send(request)
+        }
+    }
+
+    /// Drops the include query parameter, like a request that does not ask for it
+    struct WithoutIncludes(FakeTransip);
+
+    impl Transport for WithoutIncludes {
+        fn send(&self, mut request: Request) -> transip::Result<transip::transport::Response> {
+            request.url = request.url.replace("?include=nameservers,contacts", "");
+            self.0.send(request)
+        }
+    }
+
+    #[test]
+    fn dns_entry_insert() {
+        let fake = fake();
+        with_client("dns", false, fake.clone(), |client| {
+            let entry = "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap();
+            client.dns_entry_insert(DOMAIN, entry.clone()).unwrap();
+            assert_eq!(client.dns_entry_list(DOMAIN).unwrap(), vec![entry.clone()]);
+            assert_eq!(fake.dns_entries(DOMAIN), vec![entry]);
+        });
+    }
+
+    #[test]
+    fn dns_entry_delete() {
+        let keep = "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap();
+        let remove = "www 300 A 192.0.2.2".parse::<DnsEntry>().unwrap();
+        let fake = fake()
+            .with_dns_entry(DOMAIN, keep.clone())
+            .with_dns_entry(DOMAIN, remove.clone());
+        with_client("delete", false, fake.clone(), |client| {
+            client.dns_entry_delete(DOMAIN, remove.clone()).unwrap();
+            assert_eq!(fake.dns_entries(DOMAIN), vec![keep]);
+            let error = client.dns_entry_delete(DOMAIN, remove).unwrap_err();
+            assert!(error.is_not_found());
+        });
+    }
+
+    #[test]
+    fn dns_entry_replace_all() {
+        let fake = fake();
+        let old = "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap();
+        let fake = fake.with_dns_entry(DOMAIN, old.clone());
+        with_client("replace", false, fake.clone(), |client| {
+            let new = vec![
+                "www 300 A 192.0.2.2".parse::<DnsEntry>().unwrap(),
+                "@ 300 TXT v=spf1 -all".parse::<DnsEntry>().unwrap(),
+            ];
+            let snapshot = Snapshot::replace(client, DOMAIN, new.clone()).unwrap();
+            assert_eq!(fake.dns_entries(DOMAIN), new);
+            snapshot.restore(client).unwrap();
+            assert_eq!(fake.dns_entries(DOMAIN), vec![old]);
+        });
+    }
+
+    #[test]
+    fn dns_entry_upsert() {
+        let fake = fake();
+        with_client("upsert", false, fake.clone(), |client| {
+            let first = "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap();
+            let second = "www 300 A 192.0.2.2".parse::<DnsEntry>().unwrap();
+            client.dns_entry_upsert(DOMAIN, first.clone()).unwrap();
+            assert_eq!(fake.dns_entries(DOMAIN), vec![first]);
+            client.dns_entry_upsert(DOMAIN, second.clone()).unwrap();
+            assert_eq!(fake.dns_entries(DOMAIN), vec![second]);
+        });
+    }
+
+    #[test]
+    fn mailbox_delete() {
+        with_client("mailbox", false, fake(), |client| {
+            let insert = "info secret 1024".parse().unwrap();
+            client.mailbox_insert(DOMAIN, insert).unwrap();
+            let mailbox = client.mailbox_item(DOMAIN, "info@example.com").unwrap();
+            assert_eq!(mailbox.local_part, "info");
+            client.mailbox_delete(DOMAIN, "info@example.com").unwrap();
+            assert!(client.mailbox_list(DOMAIN).unwrap().is_empty());
+            let error = client
+                .mailbox_delete(DOMAIN, "info@example.com")
+                .unwrap_err();
+            assert!(error.is_not_found());
+        });
+    }
+
+    #[test]
+    fn mail_forward() {
+        with_client("forward", false, fake(), |client| {
+            let insert = "info someone@example.org".parse().unwrap();
+            client.mailforward_insert(DOMAIN, insert).unwrap();
+            let forwards = client.mailforward_list(DOMAIN).unwrap();
+            assert_eq!(forwards[0].forward_to, "someone@example.org");
+            let id = forwards[0].id.to_string();
+            client.mailforward_delete(DOMAIN, &id).unwrap();
+            assert!(client.mailforward_list(DOMAIN).unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn domain_includes() {
+        with_client("includes", false, fake(), |client| {
+            assert_eq!(client.domain_item(DOMAIN).unwrap().nameservers.len(), 3);
+            assert_eq!(client.domain_list().unwrap()[0].nameservers.len(), 3);
+        });
+        with_client("no-includes", false, WithoutIncludes(fake()), |client| {
+            assert!(client.domain_item(DOMAIN).unwrap().nameservers.is_empty());
+        });
+    }
+
+    #[test]
+    fn unknown_domain() {
+        with_client("unknown", false, fake(), |client| {
+            let error = client.dns_entry_list("example.org").unwrap_err();
+            assert!(error.is_not_found());
+        });
+    }
+
+    #[test]
+    fn test_mode() {
+        let fake = fake();
+        with_client("test-mode", false, fake.clone(), |client| {
+            let client = client.clone().with_test_mode(true);
+            let entry = "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap();
+            client.dns_entry_insert(DOMAIN, entry).unwrap();
+            assert!(fake.dns_entries(DOMAIN).is_empty());
+            let error = client.dns_entry_list("example.org").unwrap_err();
+            assert!(error.is_not_found());
+        });
+    }
+
+    #[test]
+    fn read_only_token() {
+        with_client("read-only", true, fake(), |client| {
+            assert_eq!(client.api_test().unwrap(), "pong");
+            let entry = "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap();
+            let error = client.dns_entry_insert(DOMAIN, entry).unwrap_err();
+            assert_eq!(error.status(), Some(403));
+        });
+    }
+
+    #[test]
+    fn missing_token() {
+        let response = fake().handle(&Request::new(
+            Method::Get,
+            "https://api.transip.nl/v6/api-test",
+        ));
+        assert_eq!(response.status, 401);
+    }
+
+    #[test]
+    fn invalid_signature() {
+        let fake = FakeTransip::new().with_account(LOGIN, vec![0u8; 16]);
+        let request = Request::new(Method::Post, "https://api.transip.nl/v6/auth")
+            .header("Signature", "c2lnbmF0dXJl")
+            .body(br#"{"login":"fake","nonce":"1"}"#.to_vec());
+        assert_eq!(fake.handle(&request).status, 401);
+    }
+
+    #[test]
+    fn paginated_list() {
+        let fake = fake().with_vps("second-vps").with_vps("third-vps");
+        with_client("pages", false, fake, |client| {
+            let names = client
+                .vps_iter()
+                .map(|vps| vps.map(|vps| vps.name))
+                .collect::<transip::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(names, vec!["example-vps", "second-vps", "third-vps"]);
+        });
+    }
+
+    #[test]
+    fn served_over_http() {
+        let fake = fake();
+        let server = fake.serve().unwrap();
+        let transport = Redirect {
+            url: server.url(),
+            inner: UreqTransport::config_builder(transip::IpFamily::Any)
+                .build()
+                .new_agent()
+                .into(),
+        };
+        with_client("http", false, transport, |client| {
+            assert_eq!(client.api_test().unwrap(), "pong");
+            client.vps_stop("example-vps").unwrap();
+            assert_eq!(client.vps("example-vps").unwrap().status, "stopped");
+        });
+    }
+}
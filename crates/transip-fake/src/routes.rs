@@ -0,0 +1,392 @@
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use transip::api::{
+    dns::{DnsEntry, DnsEntryItem, DnsEntryList},
+    domain::Domain,
+    email::{MailForward, MailForwardInsert, Mailbox, MailboxInsert},
+    vps::Vps,
+};
+use transip::transport::{Method, Request, Response};
+
+use crate::auth::{AuthRequest, verify_signature};
+use crate::state::{DomainState, State};
+use crate::{ApiError, PREFIX};
+
+type Reply = Result<Response, ApiError>;
+
+fn ok(body: Value) -> Reply {
+    Ok(Response::json(200, body.to_string()))
+}
+
+fn created() -> Reply {
+    Ok(Response::new(201))
+}
+
+fn no_content() -> Reply {
+    Ok(Response::new(204))
+}
+
+fn header<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+    request
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn body<T: DeserializeOwned>(request: &Request) -> Result<T, ApiError> {
+    let body = request
+        .body
+        .as_deref()
+        .filter(|body| !body.is_empty())
+        .ok_or_else(|| ApiError::bad_request("Request body is missing"))?;
+    serde_json::from_slice(body)
+        .map_err(|error| ApiError::bad_request(format!("Invalid request body: {error}")))
+}
+
+fn query_parameter<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+    request
+        .path()
+        .split_once('?')?
+        .1
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Nameservers and contacts are only filled in when the request includes them
+fn included(request: &Request, domain: &Domain) -> Domain {
+    let includes = query_parameter(request, "include")
+        .map(|include| include.split(',').collect::<Vec<_>>())
+        .unwrap_or_default();
+    let mut domain = domain.clone();
+    if !includes.contains(&"nameservers") {
+        domain.nameservers.clear();
+    }
+    if !includes.contains(&"contacts") {
+        domain.contacts.clear();
+    }
+    domain
+}
+
+/// Serializes a list under `key`.
+/// If the request has a `page` query parameter, only that page is returned,
+/// together with the `_links` to the other pages.
+fn list<T: Serialize>(request: &Request, key: &str, items: &[T]) -> Reply {
+    let path = request
+        .path()
+        .split_once('?')
+        .map_or(request.path(), |(path, _)| path);
+    let parameter = |name: &str| query_parameter(request, name).map(|value| value.parse::<usize>());
+    let Some(page) = parameter("page") else {
+        return ok(json!({ key: items, "_links": [] }));
+    };
+    let page = page
+        .ok()
+        .filter(|page| *page > 0)
+        .ok_or_else(|| ApiError::bad_request("Invalid page"))?;
+    let page_size = parameter("pageSize")
+        .unwrap_or(Ok(25))
+        .ok()
+        .filter(|page_size| *page_size > 0)
+        .ok_or_else(|| ApiError::bad_request("Invalid pageSize"))?;
+    let last = items.len().div_ceil(page_size).max(1);
+    let link = |rel: &str, page: usize| json!({ "rel": rel, "link": format!("{path}?page={page}&pageSize={page_size}") });
+    let mut links = vec![link("self", page), link("first", 1)];
+    if page > 1 {
+        links.push(link("previous", page - 1));
+    }
+    if page < last {
+        links.push(link("next", page + 1));
+    }
+    links.push(link("last", last));
+    let page_items = items
+        .iter()
+        .skip((page - 1) * page_size)
+        .take(page_size)
+        .collect::<Vec<_>>();
+    ok(json!({ key: page_items, "_links": links }))
+}
+
+/// Requests with `test=1` in the query are validated, but not executed
+fn test_mode(request: &Request) -> bool {
+    request
+        .path()
+        .split_once('?')
+        .is_some_and(|(_, query)| query.split('&').any(|pair| pair == "test=1"))
+}
+
+/// Handles a single request, like the Transip api would
+pub(crate) fn handle(state: &mut State, request: &Request) -> Response {
+    if !test_mode(request) {
+        return route(state, request).unwrap_or_else(Response::from);
+    }
+    let domains = state.domains.clone();
+    let vpss = state.vpss.clone();
+    let next_forward_id = state.next_forward_id;
+    let response = route(state, request).unwrap_or_else(Response::from);
+    state.domains = domains;
+    state.vpss = vpss;
+    state.next_forward_id = next_forward_id;
+    response
+}
+
+fn route(state: &mut State, request: &Request) -> Reply {
+    let path = request.path();
+    let path = path.split_once('?').map(|(path, _)| path).unwrap_or(path);
+    let segments = path
+        .strip_prefix(PREFIX)
+        .ok_or_else(|| ApiError::not_found(format!("Path {path} not found")))?
+        .trim_matches('/')
+        .split('/')
+        .collect::<Vec<_>>();
+
+    if request.method == Method::Post && segments == ["auth"] {
+        return auth(state, request);
+    }
+    authorize(state, request)?;
+
+    match (request.method, segments.as_slice()) {
+        (Method::Get, ["api-test"]) => ok(json!({"ping": "pong"})),
+        (Method::Get, ["domains"]) => {
+            let domains = state
+                .domains
+                .values()
+                .map(|d| included(request, &d.domain))
+                .collect::<Vec<_>>();
+            list(request, "domains", &domains)
+        }
+        (Method::Get, ["domains", name]) => {
+            ok(json!({ "domain": included(request, &domain(state, name)?.domain) }))
+        }
+        (Method::Get, ["domains", name, "dns"]) => {
+            ok(json!({ "dnsEntries": domain(state, name)?.dns_entries }))
+        }
+        (Method::Post, ["domains", name, "dns"]) => dns_entry_insert(
+            domain(state, name)?,
+            body::<DnsEntryItem>(request)?.dns_entry,
+        ),
+        (Method::Patch, ["domains", name, "dns"]) => dns_entry_update(
+            domain(state, name)?,
+            body::<DnsEntryItem>(request)?.dns_entry,
+        ),
+        (Method::Put, ["domains", name, "dns"]) => {
+            domain(state, name)?.dns_entries = body::<DnsEntryList>(request)?.dns_entries;
+            no_content()
+        }
+        (Method::Delete, ["domains", name, "dns"]) => dns_entry_delete(
+            domain(state, name)?,
+            body::<DnsEntryItem>(request)?.dns_entry,
+        ),
+        (Method::Get, ["email", name, "mailboxes"]) => {
+            list(request, "mailboxes", &domain(state, name)?.mailboxes)
+        }
+        (Method::Post, ["email", name, "mailboxes"]) => {
+            mailbox_insert(domain(state, name)?, body(request)?)
+        }
+        (Method::Get, ["email", name, "mailboxes", id]) => {
+            let domain = domain(state, name)?;
+            let mailbox = domain
+                .mailboxes
+                .iter()
+                .find(|mailbox| mailbox.identifier == *id)
+                .ok_or_else(|| ApiError::not_found(format!("Mailbox {id} not found")))?;
+            ok(json!({ "mailbox": mailbox }))
+        }
+        (Method::Delete, ["email", name, "mailboxes", id]) => {
+            let domain = domain(state, name)?;
+            let count = domain.mailboxes.len();
+            domain.mailboxes.retain(|mailbox| mailbox.identifier != *id);
+            if domain.mailboxes.len() == count {
+                Err(ApiError::not_found(format!("Mailbox {id} not found")))
+            } else {
+                no_content()
+            }
+        }
+        (Method::Get, ["email", name, "mail-forwards"]) => {
+            list(request, "forwards", &domain(state, name)?.forwards)
+        }
+        (Method::Post, ["email", name, "mail-forwards"]) => {
+            let insert = body::<MailForwardInsert>(request)?;
+            state.next_forward_id += 1;
+            let id = state.next_forward_id;
+            let domain = domain(state, name)?;
+            domain.forwards.push(MailForward {
+                id,
+                local_part: insert.local_part,
+                domain: domain.domain.name.clone(),
+                status: "created".to_owned(),
+                forward_to: insert.forward_to,
+            });
+            created()
+        }
+        (Method::Get, ["email", name, "mail-forwards", id]) => {
+            let forward = domain(state, name)?
+                .forwards
+                .iter()
+                .find(|forward| forward.id.to_string() == *id)
+                .ok_or_else(|| ApiError::not_found(format!("Mail forward {id} not found")))?;
+            ok(json!({ "forward": forward }))
+        }
+        (Method::Delete, ["email", name, "mail-forwards", id]) => {
+            let domain = domain(state, name)?;
+            let count = domain.forwards.len();
+            domain
+                .forwards
+                .retain(|forward| forward.id.to_string() != *id);
+            if domain.forwards.len() == count {
+                Err(ApiError::not_found(format!("Mail forward {id} not found")))
+            } else {
+                no_content()
+            }
+        }
+        (Method::Get, ["vps"]) => list(request, "vpss", &state.vpss),
+        (Method::Get, ["vps", name]) => ok(json!({ "vps": vps(state, name)? })),
+        (Method::Patch, ["vps", name]) => vps_action(vps(state, name)?, body(request)?),
+        (Method::Put, ["vps", name]) => {
+            let update = body::<Value>(request)?
+                .get_mut("vps")
+                .map(Value::take)
+                .ok_or_else(|| ApiError::bad_request("Request body is missing vps"))
+                .and_then(|vps| {
+                    serde_json::from_value::<Vps>(vps)
+                        .map_err(|error| ApiError::bad_request(error.to_string()))
+                })?;
+            let vps = vps(state, name)?;
+            vps.description = update.description;
+            vps.tags = update.tags;
+            vps.is_customer_locked = update.is_customer_locked;
+            no_content()
+        }
+        _ => Err(ApiError::not_found(format!(
+            "{} {path} not found",
+            request.method
+        ))),
+    }
+}
+
+fn auth(state: &mut State, request: &Request) -> Reply {
+    let body = request.body.as_deref().unwrap_or_default();
+    let auth_request = serde_json::from_slice::<AuthRequest>(body)
+        .map_err(|error| ApiError::bad_request(format!("Invalid request body: {error}")))?;
+    let account = state
+        .accounts
+        .get(&auth_request.login)
+        .ok_or_else(|| ApiError::unauthorized("Username and/or signature are not valid"))?;
+    let signature = header(request, "Signature")
+        .ok_or_else(|| ApiError::unauthorized("Signature header is missing"))?;
+    verify_signature(&account.public_key, body, signature)?;
+    if !state.nonces.insert(auth_request.nonce.clone()) {
+        return Err(ApiError::unauthorized(
+            "Provided nonce has been used before",
+        ));
+    }
+    let token = state.issuer.issue(account.customer_id, &auth_request)?;
+    Ok(Response::json(201, json!({ "token": token }).to_string()))
+}
+
+fn authorize(state: &State, request: &Request) -> Result<(), ApiError> {
+    let token = header(request, "Authorization")
+        .ok_or_else(|| ApiError::unauthorized("No authorization header found"))?
+        .strip_prefix("Bearer ")
+        .ok_or_else(|| ApiError::unauthorized("Your access token is invalid."))?;
+    let claims = state.issuer.verify(token)?;
+    if claims.ro && request.method != Method::Get {
+        Err(ApiError::new(
+            403,
+            "This is a read-only token, which cannot be used for this request",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn domain<'a>(state: &'a mut State, name: &str) -> Result<&'a mut DomainState, ApiError> {
+    state
+        .domains
+        .get_mut(name)
+        .ok_or_else(|| ApiError::not_found(format!("Domain with name '{name}' not found")))
+}
+
+fn vps<'a>(state: &'a mut State, name: &str) -> Result<&'a mut Vps, ApiError> {
+    state
+        .vpss
+        .iter_mut()
+        .find(|vps| vps.name == name)
+        .ok_or_else(|| ApiError::not_found(format!("Vps with name '{name}' not found")))
+}
+
+fn dns_entry_insert(domain: &mut DomainState, entry: DnsEntry) -> Reply {
+    if domain.dns_entries.contains(&entry) {
+        Err(ApiError::new(409, "This DNS entry already exists"))
+    } else {
+        domain.dns_entries.push(entry);
+        created()
+    }
+}
+
+/// Entries are identified by name, expire and type. Only the content is updated.
+fn dns_entry_update(domain: &mut DomainState, entry: DnsEntry) -> Reply {
+    let mut matches = domain.dns_entries.iter_mut().filter(|existing| {
+        existing.name == entry.name
+            && existing.expire == entry.expire
+            && existing.entry_type == entry.entry_type
+    });
+    match (matches.next(), matches.next()) {
+        (Some(existing), None) => {
+            existing.content = entry.content;
+            no_content()
+        }
+        (Some(_), Some(_)) => Err(ApiError::new(
+            409,
+            "Multiple DNS entries match, the entry to update is ambiguous",
+        )),
+        _ => Err(ApiError::not_found("DNS entry not found")),
+    }
+}
+
+fn dns_entry_delete(domain: &mut DomainState, entry: DnsEntry) -> Reply {
+    let count = domain.dns_entries.len();
+    domain.dns_entries.retain(|existing| *existing != entry);
+    if domain.dns_entries.len() == count {
+        Err(ApiError::not_found("DNS entry not found"))
+    } else {
+        no_content()
+    }
+}
+
+fn mailbox_insert(domain: &mut DomainState, insert: MailboxInsert) -> Reply {
+    let identifier = format!("{}@{}", insert.local_part, domain.domain.name);
+    if domain
+        .mailboxes
+        .iter()
+        .any(|mailbox| mailbox.identifier == identifier)
+    {
+        return Err(ApiError::new(
+            409,
+            format!("Mailbox {identifier} already exists"),
+        ));
+    }
+    domain.mailboxes.push(Mailbox {
+        identifier,
+        local_part: insert.local_part,
+        domain: domain.domain.name.clone(),
+        available_disk_space: insert.max_disk_usage,
+        ..Default::default()
+    });
+    created()
+}
+
+fn vps_action(vps: &mut Vps, body: Value) -> Reply {
+    if vps.is_customer_locked {
+        return Err(ApiError::new(409, format!("Vps {} is locked", vps.name)));
+    }
+    let status = match body.get("action").and_then(Value::as_str) {
+        Some("start") | Some("reset") => "running",
+        Some("stop") => "stopped",
+        _ => return Err(ApiError::bad_request("Unknown action")),
+    };
+    status.clone_into(&mut vps.status);
+    no_content()
+}
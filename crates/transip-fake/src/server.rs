@@ -0,0 +1,84 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use transip::transport::{Method, Request, Response};
+
+use crate::{FakeTransip, PREFIX};
+
+/// The fake served over http on a random local port.
+/// The server stops when this value is dropped.
+pub struct FakeServer {
+    address: SocketAddr,
+    server: Arc<tiny_http::Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeServer {
+    pub(crate) fn start(fake: FakeTransip) -> std::io::Result<Self> {
+        let server = tiny_http::Server::http("127.0.0.1:0")
+            .map(Arc::new)
+            .map_err(std::io::Error::other)?;
+        let address = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| std::io::Error::other("Not listening on an ip address"))?;
+        let thread = std::thread::spawn({
+            let server = server.clone();
+            move || {
+                for request in server.incoming_requests() {
+                    serve(&fake, address, request);
+                }
+            }
+        });
+        Ok(Self {
+            address,
+            server,
+            thread: Some(thread),
+        })
+    }
+
+    /// Url prefix of the api, for example `http://127.0.0.1:34567/v6`
+    pub fn url(&self) -> String {
+        format!("http://{}{}", self.address, PREFIX)
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(fake: &FakeTransip, address: SocketAddr, mut request: tiny_http::Request) {
+    let response = match request.method().as_str().parse::<Method>() {
+        Ok(method) => {
+            let mut body = vec![];
+            let mut transip_request =
+                Request::new(method, format!("http://{}{}", address, request.url()));
+            for header in request.headers() {
+                transip_request = transip_request.header(&header.field, &header.value);
+            }
+            match request.as_reader().read_to_end(&mut body) {
+                Ok(0) => fake.handle(&transip_request),
+                Ok(_) => fake.handle(&transip_request.body(body)),
+                Err(error) => Response::json(400, format!(r#"{{"error":"{error}"}}"#)),
+            }
+        }
+        Err(_) => Response::json(405, r#"{"error":"Method not allowed"}"#),
+    };
+
+    let mut http_response =
+        tiny_http::Response::from_data(response.body).with_status_code(response.status);
+    for (name, value) in response.headers {
+        if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            http_response.add_header(header);
+        }
+    }
+    if let Err(error) = request.respond(http_response) {
+        tracing::warn!("Error {} sending response", error);
+    }
+}
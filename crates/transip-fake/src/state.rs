@@ -0,0 +1,96 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{Months, Utc};
+use transip::api::{
+    dns::DnsEntry,
+    domain::{Domain, NameServer},
+    email::{MailForward, Mailbox},
+    vps::Vps,
+};
+
+use crate::auth::Issuer;
+
+const NAMESERVERS: [&str; 3] = ["ns0.transip.net", "ns1.transip.nl", "ns2.transip.eu"];
+
+pub(crate) struct Account {
+    /// RSA public key in PKCS#1 DER format
+    pub public_key: Vec<u8>,
+    pub customer_id: i64,
+}
+
+#[derive(Clone)]
+pub(crate) struct DomainState {
+    pub domain: Domain,
+    pub dns_entries: Vec<DnsEntry>,
+    pub mailboxes: Vec<Mailbox>,
+    pub forwards: Vec<MailForward>,
+}
+
+impl DomainState {
+    pub fn new(name: &str) -> Self {
+        let today = Utc::now().date_naive();
+        Self {
+            domain: Domain {
+                name: name.to_owned(),
+                nameservers: NAMESERVERS
+                    .into_iter()
+                    .map(|hostname| NameServer {
+                        hostname: hostname.to_owned(),
+                        ipv4: None,
+                        ipv6: None,
+                    })
+                    .collect(),
+                contacts: vec![],
+                auth_code: None,
+                is_transfer_locked: false,
+                registration_date: today.to_string(),
+                renewal_date: (today + Months::new(12)).to_string(),
+                is_whitelabel: false,
+                cancellation_date: None,
+                cancellation_status: None,
+                is_dns_only: false,
+                tags: vec![],
+                can_edit_dns: true,
+                has_auto_dns: false,
+                has_dns_sec: false,
+                status: "registered".to_owned(),
+            },
+            dns_entries: vec![],
+            mailboxes: vec![],
+            forwards: vec![],
+        }
+    }
+}
+
+pub(crate) fn new_vps(name: &str, number: usize) -> Vps {
+    Vps {
+        name: name.to_owned(),
+        uuid: format!("00000000-0000-4000-8000-{number:012x}"),
+        description: String::default(),
+        product_name: "vps-bladevps-x4".to_owned(),
+        operating_system: "Ubuntu 24.04".to_owned(),
+        disk_size: 157286400,
+        memory_size: 4194304,
+        cpus: 2,
+        status: "running".to_owned(),
+        ip_address: format!("192.0.2.{}", number % 254 + 1),
+        mac_address: format!("52:54:00:00:00:{:02x}", number % 256),
+        current_snapshots: 0,
+        max_snapshots: 1,
+        is_locked: false,
+        is_blocked: false,
+        is_customer_locked: false,
+        availability_zone: "ams0".to_owned(),
+        tags: vec![],
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct State {
+    pub issuer: Issuer,
+    pub accounts: BTreeMap<String, Account>,
+    pub nonces: HashSet<String>,
+    pub domains: BTreeMap<String, DomainState>,
+    pub vpss: Vec<Vps>,
+    pub next_forward_id: u64,
+}
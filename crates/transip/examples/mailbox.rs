@@ -7,7 +7,7 @@ const EMAIL_ADDRESS: &str = "info@paulmin.nl";
 
 fn main() -> Result<()> {
     env_logger::init();
-    let mut client = configuration_from_environment().and_then(Client::try_from)?;
+    let client = configuration_from_environment().and_then(Client::try_from)?;
     let mailboxes = client.mailbox_list("paulmin.nl")?;
     println!("{}", to_string_pretty(&mailboxes)?);
 
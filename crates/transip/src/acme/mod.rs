@@ -0,0 +1,454 @@
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::api::dns::{ACME_CHALLENGE, DnsApi, DnsEntry, DnsRecord};
+use crate::api::domain::{Domain, NameServer};
+use crate::{Error, Result};
+
+mod query;
+
+const DNS_PORT: u16 = 53;
+const TTL: u32 = 60;
+
+/// How long and how often the nameservers are asked for a published [`Challenge`]
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use transip::acme::Propagation;
+///
+/// let propagation = Propagation {
+///     timeout: Duration::from_secs(600),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Propagation {
+    /// Longest time to wait until every nameserver answers with the challenge
+    pub timeout: Duration,
+    /// Delay between two rounds of queries
+    pub interval: Duration,
+    /// Longest time to wait for the answer to a single query
+    pub query_timeout: Duration,
+}
+
+impl Default for Propagation {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(300),
+            interval: Duration::from_secs(5),
+            query_timeout: Duration::from_secs(3),
+        }
+    }
+}
+
+/// The `_acme-challenge` TXT entries that prove control over a name of a certificate.
+///
+/// A wildcard and its apex, like `*.example.com` and `example.com`, share the same
+/// challenge name, so a single challenge can hold several values.
+///
+/// Example
+/// ```no_run
+/// # #[cfg(feature = "ureq")] {
+/// use transip::acme::{Challenge, Propagation, nameserver_addresses};
+/// use transip::api::domain::DomainApi;
+/// use transip::{configuration_from_environment, Client};
+///
+/// let client = configuration_from_environment()
+///     .and_then(Client::try_from)
+///     .expect("No cliënt");
+///
+/// let challenge = Challenge::new("example.com", "*.example.com", &["first", "second"])
+///     .expect("Not in domain");
+/// challenge.publish(&client).expect("Publish failed");
+/// let domain = client.domain_item("example.com").expect("No domain");
+/// let nameservers = nameserver_addresses(&domain).expect("No nameservers");
+/// challenge
+///     .wait(&nameservers, &Propagation::default())
+///     .expect("Not visible");
+/// // Let the acme server validate the challenge, then
+/// challenge.cleanup(&client).expect("Cleanup failed");
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Challenge {
+    pub domain_name: String,
+    /// Name of the entries, relative to the domain, like `_acme-challenge.www`
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+impl Challenge {
+    /// A challenge for `identifier`, a name in the certificate like `example.com`,
+    /// `www.example.com` or `*.example.com`, which must be part of the domain `domain_name`
+    pub fn new<S: AsRef<str>>(domain_name: &str, identifier: &str, values: &[S]) -> Result<Self> {
+        let domain_name = normalize(domain_name);
+        let identifier = normalize(identifier);
+        let identifier = identifier.strip_prefix("*.").unwrap_or(&identifier);
+        let name = if identifier == domain_name {
+            ACME_CHALLENGE.to_owned()
+        } else {
+            identifier
+                .strip_suffix(&domain_name)
+                .and_then(|subdomain| subdomain.strip_suffix('.'))
+                .map(|subdomain| format!("{ACME_CHALLENGE}.{subdomain}"))
+                .ok_or_else(|| {
+                    Error::AcmeIdentifier(format!("{identifier} is not part of {domain_name}"))
+                })?
+        };
+        Ok(Self {
+            domain_name,
+            name,
+            values: values
+                .iter()
+                .map(|value| value.as_ref().to_owned())
+                .collect(),
+        })
+    }
+
+    /// Fully qualified name of the entries, like `_acme-challenge.www.example.com`
+    pub fn fqdn(&self) -> String {
+        format!("{}.{}", self.name, self.domain_name)
+    }
+
+    /// One TXT entry for every value
+    pub fn entries(&self) -> Vec<DnsEntry> {
+        self.values
+            .iter()
+            .map(|value| DnsEntry::new(&self.name, TTL, DnsRecord::TXT(value.clone())))
+            .collect()
+    }
+
+    fn contains(&self, entry: &DnsEntry) -> bool {
+        entry.name == self.name
+            && entry.entry_type == *"TXT"
+            && self.values.contains(&entry.content)
+    }
+
+    /// Inserts the entries that are not there yet
+    pub fn publish<A: DnsApi>(&self, api: &A) -> Result<()> {
+        let current = api.dns_entry_list(&self.domain_name)?;
+        for entry in self.entries() {
+            if !current.iter().any(|existing| {
+                existing.name == entry.name
+                    && existing.entry_type == entry.entry_type
+                    && existing.content == entry.content
+            }) {
+                api.dns_entry_insert(&self.domain_name, entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits until every nameserver answers with all values.
+    /// Returns [`Error::AcmeChallege`] if that takes longer than the timeout.
+    pub fn wait(&self, nameservers: &[SocketAddr], propagation: &Propagation) -> Result<()> {
+        if nameservers.is_empty() {
+            return Err(Error::Dns(format!(
+                "No nameservers for {}",
+                self.domain_name
+            )));
+        }
+        let deadline = Instant::now() + propagation.timeout;
+        let fqdn = self.fqdn();
+        loop {
+            let pending = nameservers
+                .iter()
+                .filter(|nameserver| !self.is_visible(**nameserver, &fqdn, propagation))
+                .collect::<Vec<_>>();
+            if pending.is_empty() {
+                return Ok(());
+            }
+            if Instant::now() + propagation.interval > deadline {
+                tracing::warn!("{} not visible at {:?}", fqdn, pending);
+                return Err(Error::AcmeChallege);
+            }
+            tracing::debug!("Waiting for {} at {:?}", fqdn, pending);
+            sleep(propagation.interval);
+        }
+    }
+
+    fn is_visible(&self, nameserver: SocketAddr, fqdn: &str, propagation: &Propagation) -> bool {
+        match query::txt(nameserver, fqdn, propagation.query_timeout) {
+            Ok(records) => self.values.iter().all(|value| records.contains(value)),
+            Err(error) => {
+                tracing::debug!("Query {} at {} failed with {}", fqdn, nameserver, error);
+                false
+            }
+        }
+    }
+
+    /// Deletes the entries of this challenge, other challenges are left alone
+    pub fn cleanup<A: DnsApi>(&self, api: &A) -> Result<()> {
+        api.dns_entry_delete_all(&self.domain_name, |entry| self.contains(entry))
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_lowercase()
+}
+
+/// Address of every nameserver of the domain.
+/// The ip address from the api is used if there is one, otherwise the hostname is resolved.
+///
+/// The domain must come with its nameservers, like the ones from
+/// [`domain_item`](crate::api::domain::DomainApi::domain_item) and
+/// [`domain_list`](crate::api::domain::DomainApi::domain_list).
+/// A domain without nameservers is an error, so propagation is never checked against none.
+pub fn nameserver_addresses(domain: &Domain) -> Result<Vec<SocketAddr>> {
+    if domain.nameservers.is_empty() {
+        return Err(Error::Dns(format!("No nameservers for {}", domain.name)));
+    }
+    domain.nameservers.iter().map(nameserver_address).collect()
+}
+
+fn nameserver_address(nameserver: &NameServer) -> Result<SocketAddr> {
+    let ip = [&nameserver.ipv4, &nameserver.ipv6]
+        .into_iter()
+        .flatten()
+        .find(|ip| !ip.is_empty());
+    match ip {
+        Some(ip) => Ok(SocketAddr::new(ip.parse::<IpAddr>()?, DNS_PORT)),
+        None => (nameserver.hostname.as_str(), DNS_PORT)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| Error::Dns(format!("No address for {}", nameserver.hostname))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{SocketAddr, UdpSocket};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread::{sleep, spawn};
+    use std::time::Duration;
+
+    use super::{Challenge, Propagation, nameserver_address, nameserver_addresses};
+    use crate::Error;
+    use crate::api::domain::{DomainApi, NameServer};
+    use crate::client::Client;
+    use crate::transport::{MemoryTransport, Method, Response};
+
+    /// A local nameserver that answers TXT queries with the records it is given
+    struct StandIn {
+        address: SocketAddr,
+        records: Arc<Mutex<Vec<(String, String)>>>,
+        stop: Arc<AtomicBool>,
+    }
+
+    impl StandIn {
+        fn start() -> Self {
+            let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+            socket
+                .set_read_timeout(Some(Duration::from_millis(20)))
+                .unwrap();
+            let stand_in = Self {
+                address: socket.local_addr().unwrap(),
+                records: Arc::default(),
+                stop: Arc::default(),
+            };
+            let records = stand_in.records.clone();
+            let stop = stand_in.stop.clone();
+            spawn(move || {
+                let mut buffer = [0; 512];
+                while !stop.load(Ordering::Relaxed) {
+                    if let Ok((length, peer)) = socket.recv_from(&mut buffer) {
+                        let response = answer(&buffer[..length], &records.lock().unwrap());
+                        socket.send_to(&response, peer).unwrap();
+                    }
+                }
+            });
+            stand_in
+        }
+
+        fn publish(&self, name: &str, value: &str) {
+            self.records
+                .lock()
+                .unwrap()
+                .push((name.to_owned(), value.to_owned()));
+        }
+    }
+
+    impl Drop for StandIn {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn answer(query: &[u8], records: &[(String, String)]) -> Vec<u8> {
+        let mut position = 12;
+        let mut labels = vec![];
+        while query[position] != 0 {
+            let length = usize::from(query[position]);
+            labels.push(String::from_utf8_lossy(
+                &query[position + 1..position + 1 + length],
+            ));
+            position += 1 + length;
+        }
+        let name = labels.join(".");
+        let values = records
+            .iter()
+            .filter(|(record, _)| *record == name)
+            .map(|(_, value)| value.as_bytes())
+            .collect::<Vec<_>>();
+
+        let mut response = query[..2].to_vec();
+        response.extend_from_slice(&[0x84, 0, 0, 1, 0, values.len() as u8, 0, 0, 0, 0]);
+        response.extend_from_slice(&query[12..position + 5]);
+        for value in values {
+            response.extend_from_slice(&[0xc0, 0x0c, 0, 16, 0, 1, 0, 0, 0, 60, 0]);
+            response.push(value.len() as u8 + 1);
+            response.push(value.len() as u8);
+            response.extend_from_slice(value);
+        }
+        response
+    }
+
+    fn fast() -> Propagation {
+        Propagation {
+            timeout: Duration::from_millis(500),
+            interval: Duration::from_millis(10),
+            query_timeout: Duration::from_millis(100),
+        }
+    }
+
+    #[test]
+    fn names() {
+        let apex = Challenge::new("example.com", "example.com.", &["a"]).unwrap();
+        assert_eq!(apex.name, "_acme-challenge");
+        let wildcard = Challenge::new("example.com", "*.Example.com", &["a"]).unwrap();
+        assert_eq!(wildcard.name, "_acme-challenge");
+        let subdomain = Challenge::new("example.com", "*.www.example.com", &["a"]).unwrap();
+        assert_eq!(subdomain.name, "_acme-challenge.www");
+        assert_eq!(subdomain.fqdn(), "_acme-challenge.www.example.com");
+        assert!(subdomain.entries()[0].is_acme_challenge());
+        assert!(matches!(
+            Challenge::new("example.com", "notexample.com", &["a"]),
+            Err(Error::AcmeIdentifier(_))
+        ));
+        assert!(matches!(
+            Challenge::new("example.com", "example.org", &["a"]),
+            Err(Error::AcmeIdentifier(_))
+        ));
+    }
+
+    #[test]
+    fn publish_and_cleanup() {
+        let transport = MemoryTransport::default()
+            .respond(
+                Method::Get,
+                "/domains/example.com/dns",
+                Response::json(
+                    200,
+                    r#"{"dnsEntries":[
+                        {"name":"_acme-challenge","expire":60,"type":"TXT","content":"first"},
+                        {"name":"_acme-challenge","expire":60,"type":"TXT","content":"stale"}
+                    ]}"#,
+                ),
+            )
+            .respond(Method::Post, "/domains/example.com/dns", Response::new(201))
+            .respond(
+                Method::Delete,
+                "/domains/example.com/dns",
+                Response::new(204),
+            );
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone());
+        let challenge =
+            Challenge::new("example.com", "*.example.com", &["first", "second"]).unwrap();
+
+        challenge.publish(&client).unwrap();
+        challenge.cleanup(&client).unwrap();
+        let requests = transport
+            .requests()
+            .into_iter()
+            .filter(|request| request.method != Method::Get)
+            .map(|request| {
+                (
+                    request.method,
+                    String::from_utf8(request.body.unwrap()).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            requests,
+            vec![
+                (
+                    Method::Post,
+                    r#"{"dnsEntry":{"name":"_acme-challenge","expire":60,"type":"TXT","content":"second"}}"#
+                        .to_owned()
+                ),
+                (
+                    Method::Delete,
+                    r#"{"dnsEntry":{"name":"_acme-challenge","expire":60,"type":"TXT","content":"first"}}"#
+                        .to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn wait_until_visible() {
+        let first = StandIn::start();
+        let second = StandIn::start();
+        let challenge = Challenge::new("example.com", "www.example.com", &["one", "two"]).unwrap();
+        first.publish("_acme-challenge.www.example.com", "one");
+        first.publish("_acme-challenge.www.example.com", "two");
+        second.publish("_acme-challenge.www.example.com", "one");
+        let records = second.records.clone();
+        spawn(move || {
+            sleep(Duration::from_millis(50));
+            records.lock().unwrap().push((
+                "_acme-challenge.www.example.com".to_owned(),
+                "two".to_owned(),
+            ));
+        });
+        challenge
+            .wait(&[first.address, second.address], &fast())
+            .unwrap();
+    }
+
+    #[test]
+    fn wait_timeout() {
+        let stand_in = StandIn::start();
+        stand_in.publish("_acme-challenge.example.com", "other");
+        let challenge = Challenge::new("example.com", "example.com", &["one"]).unwrap();
+        let error = challenge.wait(&[stand_in.address], &fast()).unwrap_err();
+        assert!(matches!(error, Error::AcmeChallege));
+        assert!(challenge.wait(&[], &fast()).is_err());
+    }
+
+    #[test]
+    fn nameserver_ip() {
+        let nameserver = NameServer {
+            hostname: "ns0.transip.net".to_owned(),
+            ipv4: Some("195.135.195.195".to_owned()),
+            ipv6: None,
+        };
+        assert_eq!(
+            nameserver_address(&nameserver).unwrap(),
+            "195.135.195.195:53".parse().unwrap()
+        );
+        let nameserver = NameServer {
+            hostname: "localhost".to_owned(),
+            ipv4: Some(String::new()),
+            ipv6: None,
+        };
+        assert_eq!(nameserver_address(&nameserver).unwrap().port(), 53);
+    }
+
+    #[test]
+    fn nameservers_of_domain() {
+        let mut domain = Client::fixture("domain")
+            .domain_item("transipdemo.be")
+            .unwrap();
+        for (nameserver, ip) in domain.nameservers.iter_mut().zip(1..) {
+            nameserver.ipv4 = Some(format!("192.0.2.{ip}"));
+        }
+        assert_eq!(nameserver_addresses(&domain).unwrap().len(), 3);
+        domain.nameservers.clear();
+        assert!(nameserver_addresses(&domain).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::time::Duration;
+
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::{Error, Result};
+
+const HEADER_LENGTH: usize = 12;
+/// Plain dns over udp, without EDNS, is limited to 512 bytes
+const UDP_LENGTH: usize = 512;
+const TXT: u16 = 16;
+const IN: u16 = 1;
+const RESPONSE: u16 = 0x8000;
+const TRUNCATED: u16 = 0x0200;
+const NXDOMAIN: u16 = 3;
+const POINTER: u8 = 0xc0;
+
+fn dns_error(message: impl Into<String>) -> Error {
+    Error::Dns(message.into())
+}
+
+/// Asks the nameserver for the TXT records of `name`, without recursion.
+/// Falls back to tcp if the answer does not fit in a udp packet.
+/// A name that does not exist has no records.
+pub(crate) fn txt(nameserver: SocketAddr, name: &str, timeout: Duration) -> Result<Vec<String>> {
+    let mut id = [0; 2];
+    SystemRandom::new()
+        .fill(&mut id)
+        .map_err(|_| dns_error("No random query id"))?;
+    let id = u16::from_be_bytes(id);
+    let query = encode(id, name)?;
+    let response = match udp(nameserver, &query, id, timeout)? {
+        response if flags(&response) & TRUNCATED == TRUNCATED => tcp(nameserver, &query, timeout)?,
+        response => response,
+    };
+    decode(id, &response)
+}
+
+fn udp(nameserver: SocketAddr, query: &[u8], id: u16, timeout: Duration) -> Result<Vec<u8>> {
+    let local = match nameserver {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect(nameserver)?;
+    socket.send(query)?;
+    let mut buffer = [0; UDP_LENGTH];
+    loop {
+        let length = socket.recv(&mut buffer)?;
+        // Late answers to an earlier query are skipped
+        if length >= HEADER_LENGTH && buffer[..2] == id.to_be_bytes() {
+            return Ok(buffer[..length].to_vec());
+        }
+    }
+}
+
+fn tcp(nameserver: SocketAddr, query: &[u8], timeout: Duration) -> Result<Vec<u8>> {
+    let mut stream = TcpStream::connect_timeout(&nameserver, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let length = u16::try_from(query.len()).map_err(|_| dns_error("Query too long"))?;
+    stream.write_all(&[&length.to_be_bytes(), query].concat())?;
+    let mut length = [0; 2];
+    stream.read_exact(&mut length)?;
+    let mut response = vec![0; usize::from(u16::from_be_bytes(length))];
+    stream.read_exact(&mut response)?;
+    Ok(response)
+}
+
+fn flags(packet: &[u8]) -> u16 {
+    u16::from_be_bytes([packet[2], packet[3]])
+}
+
+/// A query for the TXT records of `name`, with recursion not desired
+fn encode(id: u16, name: &str) -> Result<Vec<u8>> {
+    let mut query = Vec::with_capacity(HEADER_LENGTH + name.len() + 6);
+    query.extend_from_slice(&id.to_be_bytes());
+    query.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        match u8::try_from(label.len()) {
+            Ok(length @ 1..=63) => {
+                query.push(length);
+                query.extend_from_slice(label.as_bytes());
+            }
+            _ => return Err(dns_error(format!("Invalid name {name}"))),
+        }
+    }
+    query.push(0);
+    if query.len() - HEADER_LENGTH > 255 {
+        return Err(dns_error(format!("Name too long {name}")));
+    }
+    query.extend_from_slice(&TXT.to_be_bytes());
+    query.extend_from_slice(&IN.to_be_bytes());
+    Ok(query)
+}
+
+/// Reads a dns packet, failing instead of panicking on a packet that is cut short
+struct Reader<'a> {
+    packet: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .packet
+            .get(self.position..self.position + length)
+            .ok_or_else(|| dns_error("Response too short"))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        self.take(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Names are not compared, the answers can only be for the name of the query
+    fn skip_name(&mut self) -> Result<()> {
+        loop {
+            match self.u8()? {
+                0 => return Ok(()),
+                length if length & POINTER == POINTER => return self.u8().map(|_| ()),
+                length => self.take(usize::from(length))?,
+            };
+        }
+    }
+}
+
+/// The TXT records in the answer section, each joined from its character strings
+fn decode(id: u16, packet: &[u8]) -> Result<Vec<String>> {
+    let mut reader = Reader {
+        packet,
+        position: 0,
+    };
+    if reader.u16()? != id {
+        return Err(dns_error("Response to another query"));
+    }
+    let flags = reader.u16()?;
+    if flags & RESPONSE != RESPONSE {
+        return Err(dns_error("Not a response"));
+    }
+    match flags & 0x000f {
+        0 => {}
+        NXDOMAIN => return Ok(vec![]),
+        code => return Err(dns_error(format!("Response code {code}"))),
+    }
+    let questions = reader.u16()?;
+    let answers = reader.u16()?;
+    reader.take(4)?;
+    for _ in 0..questions {
+        reader.skip_name()?;
+        reader.take(4)?;
+    }
+
+    let mut records = vec![];
+    for _ in 0..answers {
+        reader.skip_name()?;
+        let record_type = reader.u16()?;
+        let class = reader.u16()?;
+        reader.take(4)?;
+        let length = reader.u16()?;
+        let mut data = Reader {
+            packet: reader.take(usize::from(length))?,
+            position: 0,
+        };
+        if record_type == TXT && class == IN {
+            let mut record = vec![];
+            while data.position < data.packet.len() {
+                let length = data.u8()?;
+                record.extend_from_slice(data.take(usize::from(length))?);
+            }
+            records.push(String::from_utf8_lossy(&record).into_owned());
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode};
+
+    const QUESTION: &[u8] = b"\x0f_acme-challenge\x07example\x03com\x00\x00\x10\x00\x01";
+
+    fn response(rcode: u8, answers: &[&[u8]]) -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0x84, rcode, 0, 1, 0, answers.len() as u8];
+        packet.extend_from_slice(&[0, 0, 0, 0]);
+        packet.extend_from_slice(QUESTION);
+        for data in answers {
+            packet.extend_from_slice(&[0xc0, 0x0c, 0, 16, 0, 1, 0, 0, 0, 60, 0, data.len() as u8]);
+            packet.extend_from_slice(data);
+        }
+        packet
+    }
+
+    #[test]
+    fn encode_query() {
+        let query = encode(0x1234, "_acme-challenge.example.com.").unwrap();
+        assert_eq!(&query[..12], &[0x12, 0x34, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&query[12..], QUESTION);
+        assert!(encode(1, "www..example.com").is_err());
+        assert!(encode(1, &format!("{}.com", "a".repeat(64))).is_err());
+    }
+
+    #[test]
+    fn decode_answers() {
+        let records = decode(0x1234, &response(0, &[b"\x05first", b"\x03sec\x03ond"])).unwrap();
+        assert_eq!(records, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn decode_nxdomain() {
+        assert!(decode(0x1234, &response(3, &[])).unwrap().is_empty());
+        assert!(decode(0x1234, &response(2, &[])).is_err());
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let packet = response(0, &[b"\x05first"]);
+        assert!(decode(0x4321, &packet).is_err());
+        assert!(decode(0x1234, &packet[..packet.len() - 1]).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::api::{Link, Page, Pages};
 use crate::{
     HasName, Result,
     client::{Client, Url},
@@ -19,13 +20,31 @@ trait UrlAccount {
 /// See <https://api.transip.nl/rest/docs.html#account>
 pub trait AccountApi {
     /// See <https://api.transip.nl/rest/docs.html#account-invoices-get-1>
-    fn invoice(&mut self, invoice_number: &str) -> Result<Invoice>;
+    fn invoice(&self, invoice_number: &str) -> Result<Invoice>;
     /// See <https://api.transip.nl/rest/docs.html#account-invoiceitems-get>
-    fn invoice_items(&mut self, invoice_number: &str) -> Result<Vec<InvoiceItem>>;
+    fn invoice_items(&self, invoice_number: &str) -> Result<Vec<InvoiceItem>>;
     /// See <https://api.transip.nl/rest/docs.html#account-invoices-get>
-    fn invoice_list(&mut self) -> Result<Vec<Invoice>>;
+    fn invoice_list(&self) -> Result<Vec<Invoice>>;
+    /// Lazy version of [`invoice_list`](AccountApi::invoice_list), that requests one page at a time
+    fn invoice_iter(&self) -> Pages<'_, Invoice>;
     /// See <https://api.transip.nl/rest/docs.html#account-pdf-get>
-    fn invoice_pdf(&mut self, invoice_number: &str) -> Result<String>;
+    fn invoice_pdf(&self, invoice_number: &str) -> Result<String>;
+}
+
+/// Async version of [`AccountApi`]
+#[cfg(feature = "async")]
+pub trait AsyncAccountApi {
+    /// See <https://api.transip.nl/rest/docs.html#account-invoices-get-1>
+    fn invoice(&self, invoice_number: &str) -> impl Future<Output = Result<Invoice>> + Send;
+    /// See <https://api.transip.nl/rest/docs.html#account-invoiceitems-get>
+    fn invoice_items(
+        &self,
+        invoice_number: &str,
+    ) -> impl Future<Output = Result<Vec<InvoiceItem>>> + Send;
+    /// See <https://api.transip.nl/rest/docs.html#account-invoices-get>
+    fn invoice_list(&self) -> impl Future<Output = Result<Vec<Invoice>>> + Send;
+    /// See <https://api.transip.nl/rest/docs.html#account-pdf-get>
+    fn invoice_pdf(&self, invoice_number: &str) -> impl Future<Output = Result<String>> + Send;
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -50,6 +69,20 @@ impl HasName for Invoice {
 #[derive(Deserialize, Serialize)]
 pub struct InvoiceList {
     pub invoices: Vec<Invoice>,
+    #[serde(rename = "_links", default)]
+    pub links: Vec<Link>,
+}
+
+impl Page for InvoiceList {
+    type Item = Invoice;
+
+    fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    fn into_items(self) -> Vec<Invoice> {
+        self.invoices
+    }
 }
 
 impl Display for Invoice {
@@ -114,23 +147,52 @@ impl UrlAccount for Url {
 }
 
 impl AccountApi for Client {
-    fn invoice(&mut self, invoice_number: &str) -> Result<Invoice> {
+    fn invoice(&self, invoice_number: &str) -> Result<Invoice> {
+        self.get::<InvoiceResponse>(&self.url.invoice(invoice_number))
+            .map(|item| item.invoice)
+    }
+
+    fn invoice_items(&self, invoice_number: &str) -> Result<Vec<InvoiceItem>> {
+        self.get::<InvoiceItemList>(&self.url.invoice_items(invoice_number))
+            .map(|list| list.invoice_items)
+    }
+
+    fn invoice_list(&self) -> Result<Vec<Invoice>> {
+        self.invoice_iter().collect()
+    }
+
+    fn invoice_iter(&self) -> Pages<'_, Invoice> {
+        let url = self.url.invoices();
+        Pages::new::<InvoiceList>(self, url)
+    }
+
+    fn invoice_pdf(&self, invoice_number: &str) -> Result<String> {
+        self.get::<Pdf>(&self.url.invoice_pdf(invoice_number))
+            .map(|item| item.pdf)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncAccountApi for crate::AsyncClient {
+    async fn invoice(&self, invoice_number: &str) -> Result<Invoice> {
         self.get::<InvoiceResponse>(&self.url.invoice(invoice_number))
+            .await
             .map(|item| item.invoice)
     }
 
-    fn invoice_items(&mut self, invoice_number: &str) -> Result<Vec<InvoiceItem>> {
+    async fn invoice_items(&self, invoice_number: &str) -> Result<Vec<InvoiceItem>> {
         self.get::<InvoiceItemList>(&self.url.invoice_items(invoice_number))
+            .await
             .map(|list| list.invoice_items)
     }
 
-    fn invoice_list(&mut self) -> Result<Vec<Invoice>> {
-        self.get::<InvoiceList>(&self.url.invoices())
-            .map(|list| list.invoices)
+    async fn invoice_list(&self) -> Result<Vec<Invoice>> {
+        self.get_all::<InvoiceList>(&self.url.invoices()).await
     }
 
-    fn invoice_pdf(&mut self, invoice_number: &str) -> Result<String> {
+    async fn invoice_pdf(&self, invoice_number: &str) -> Result<String> {
         self.get::<Pdf>(&self.url.invoice_pdf(invoice_number))
+            .await
             .map(|item| item.pdf)
     }
 }
@@ -142,7 +204,7 @@ mod test {
 
     #[test]
     fn list() {
-        let mut client = Client::demo();
+        let client = Client::fixture("account");
         let list = client.invoice_list().unwrap();
         let names = list.names();
         let expected: Vec<String> = vec![];
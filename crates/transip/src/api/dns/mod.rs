@@ -0,0 +1,542 @@
+use crate::Error;
+use std::str::FromStr;
+
+use crate::error::ResultExt;
+use crate::{
+    HasName, Result,
+    client::{Client, Url},
+};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+pub use reconcile::{Change, Plan};
+pub use record::DnsRecord;
+pub use snapshot::Snapshot;
+pub use zone::Zone;
+
+mod reconcile;
+mod record;
+mod snapshot;
+mod zone;
+
+const DOMAINS: &str = "domains";
+const DNS: &str = "dns";
+pub(crate) const ACME_CHALLENGE: &str = "_acme-challenge";
+const TRANSIP_DOMAIN_KEY_PREFIX: &str = "transip-";
+const DOMAIN_KEY_SUFFIX: &str = "._domainkey";
+
+trait UrlDomain {
+    fn domain_dns(&self, domain_name: &str) -> String;
+}
+
+/// See <https://api.transip.nl/rest/docs.html#domains>
+pub trait DnsApi {
+    /// See <https://api.transip.nl/rest/docs.html#domains-dns-delete>
+    fn dns_entry_delete(&self, domain_name: &str, entry: DnsEntry) -> Result<()>;
+    /// Delete all entries which comply to Filter F
+    fn dns_entry_delete_all<F>(&self, domain_name: &str, f: F) -> Result<()>
+    where
+        F: Fn(&DnsEntry) -> bool;
+    /// See <https://api.transip.nl/rest/docs.html#domains-dns-get>
+    fn dns_entry_list(&self, domain_name: &str) -> Result<Vec<DnsEntry>>;
+    /// See <https://api.transip.nl/rest/docs.html#domains-dns-post>
+    fn dns_entry_insert(&self, domain_name: &str, entry: DnsEntry) -> Result<()>;
+    /// Replace all entries of the domain in a single request.
+    /// Use [`Snapshot::replace`] to restore the entries if the replacement fails.
+    /// See <https://api.transip.nl/rest/docs.html#domains-dns-put>
+    fn dns_entry_replace_all(&self, domain_name: &str, entries: Vec<DnsEntry>) -> Result<()>;
+    /// Change the content of the entry with the same name, type and ttl.
+    /// The api returns a conflict if more than one entry matches.
+    /// See <https://api.transip.nl/rest/docs.html#domains-dns-patch>
+    fn dns_entry_update(&self, domain_name: &str, entry: DnsEntry) -> Result<()>;
+    /// Insert the entry if no entry with the same name, type and ttl exists, update it otherwise
+    fn dns_entry_upsert(&self, domain_name: &str, entry: DnsEntry) -> Result<()>;
+}
+
+/// Async version of [`DnsApi`]
+#[cfg(feature = "async")]
+pub trait AsyncDnsApi {
+    /// See <https://api.transip.nl/rest/docs.html#domains-dns-delete>
+    fn dns_entry_delete(
+        &self,
+        domain_name: &str,
+        entry: DnsEntry,
+    ) -> impl Future<Output = Result<()>> + Send;
+    /// Delete all entries which comply to Filter F
+    fn dns_entry_delete_all<F>(
+        &self,
+        domain_name: &str,
+        f: F,
+    ) -> impl Future<Output = Result<()>> + Send
+    where
+        F: Fn(&DnsEntry) -> bool + Send;
+    /// See <https://api.transip.nl/rest/docs.html#domains-dns-get>
+    fn dns_entry_list(
+        &self,
+        domain_name: &str,
+    ) -> impl Future<Output = Result<Vec<DnsEntry>>> + Send;
+    /// See <https://api.transip.nl/rest/docs.html#domains-dns-post>
+    fn dns_entry_insert(
+        &self,
+        domain_name: &str,
+        entry: DnsEntry,
+    ) -> impl Future<Output = Result<()>> + Send;
+    /// See <https://api.transip.nl/rest/docs.html#domains-dns-put>
+    fn dns_entry_replace_all(
+        &self,
+        domain_name: &str,
+        entries: Vec<DnsEntry>,
+    ) -> impl Future<Output = Result<()>> + Send;
+    /// See <https://api.transip.nl/rest/docs.html#domains-dns-patch>
+    fn dns_entry_update(
+        &self,
+        domain_name: &str,
+        entry: DnsEntry,
+    ) -> impl Future<Output = Result<()>> + Send;
+    /// Insert the entry if no entry with the same name, type and ttl exists, update it otherwise
+    fn dns_entry_upsert(
+        &self,
+        domain_name: &str,
+        entry: DnsEntry,
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Example
+/// ```
+/// use transip::api::dns::RecordType;
+///
+/// dbg!(RecordType::AAAA);
+/// ```
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    A,
+    AAAA,
+    ALIAS,
+    CAA,
+    CNAME,
+    DS,
+    MX,
+    NAPTR,
+    NS,
+    PTR,
+    SOA,
+    SRV,
+    SSHFP,
+    TLSA,
+    TXT,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
+pub struct DnsEntry {
+    pub name: String,
+    pub expire: u32,
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub content: String,
+}
+
+impl DnsEntry {
+    /// A TXT entry named `_acme-challenge`, or `_acme-challenge.` followed by a subdomain
+    pub fn is_acme_challenge(&self) -> bool {
+        self.entry_type == *"TXT"
+            && self
+                .name
+                .strip_prefix(ACME_CHALLENGE)
+                .is_some_and(|subdomain| subdomain.is_empty() || subdomain.starts_with('.'))
+    }
+
+    /// The dkim entries TransIP creates for its mail service, like `transip-A._domainkey`
+    pub fn is_transip_domain_key(&self) -> bool {
+        self.name.starts_with(TRANSIP_DOMAIN_KEY_PREFIX) && self.name.ends_with(DOMAIN_KEY_SUFFIX)
+    }
+
+    /// The api identifies an entry to update by its name, type and ttl
+    pub fn same_identity(&self, other: &DnsEntry) -> bool {
+        self.name == other.name
+            && self.entry_type == other.entry_type
+            && self.expire == other.expire
+    }
+
+    pub fn new_acme_challenge(expire: u32, content: &str) -> Self {
+        Self {
+            name: ACME_CHALLENGE.to_owned(),
+            expire,
+            entry_type: RecordType::TXT.to_string(),
+            content: content.to_owned(),
+        }
+    }
+}
+
+impl std::fmt::Display for DnsEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.name, self.expire, self.entry_type, self.content
+        )
+    }
+}
+
+impl FromStr for DnsEntry {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut splitted = s.split_ascii_whitespace();
+        let name = splitted
+            .next()
+            .ok_or(Error::ParseDnsEntry("name missing"))
+            .map(String::from)?;
+        let expire = splitted
+            .next()
+            .ok_or(Error::ParseDnsEntry("ttl missing"))
+            .and_then(|s| s.parse::<u32>().err_into())?;
+        let record_type = splitted
+            .next()
+            .ok_or(Error::ParseDnsEntry("record type missing"))
+            .and_then(|s| s.parse::<RecordType>().err_into())?;
+        let content = splitted.collect::<Vec<_>>().join(" ");
+        DnsRecord::parse(record_type, &content)?;
+        Ok(Self {
+            name,
+            expire,
+            entry_type: record_type.to_string(),
+            content,
+        })
+    }
+}
+
+impl HasName for DnsEntry {
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsEntryList {
+    pub dns_entries: Vec<DnsEntry>,
+}
+
+impl From<Vec<DnsEntry>> for DnsEntryList {
+    fn from(dns_entries: Vec<DnsEntry>) -> Self {
+        Self { dns_entries }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsEntryItem {
+    pub dns_entry: DnsEntry,
+}
+
+impl From<DnsEntry> for DnsEntryItem {
+    fn from(dns_entry: DnsEntry) -> Self {
+        Self { dns_entry }
+    }
+}
+
+/// What [`DnsApi::dns_entry_upsert`] should do with an entry
+enum Upsert {
+    Unchanged,
+    Insert,
+    Update,
+}
+
+impl Upsert {
+    fn new(current: &[DnsEntry], entry: &DnsEntry) -> Self {
+        if current.contains(entry) {
+            Upsert::Unchanged
+        } else if current.iter().any(|existing| existing.same_identity(entry)) {
+            Upsert::Update
+        } else {
+            Upsert::Insert
+        }
+    }
+}
+
+impl UrlDomain for Url {
+    fn domain_dns(&self, domain_name: &str) -> String {
+        format!("{}{}/{}/{}", self.prefix, DOMAINS, domain_name, DNS)
+    }
+}
+
+impl DnsApi for Client {
+    fn dns_entry_delete(&self, domain_name: &str, entry: DnsEntry) -> Result<()> {
+        self.delete::<DnsEntryItem>(&self.url.domain_dns(domain_name), entry.into())
+    }
+
+    fn dns_entry_delete_all<F>(&self, domain_name: &str, f: F) -> Result<()>
+    where
+        F: Fn(&DnsEntry) -> bool,
+    {
+        for dns_entry in self.dns_entry_list(domain_name)?.into_iter().filter(f) {
+            self.dns_entry_delete(domain_name, dns_entry)?;
+        }
+        Ok(())
+    }
+
+    fn dns_entry_list(&self, domain_name: &str) -> Result<Vec<DnsEntry>> {
+        self.get::<DnsEntryList>(&self.url.domain_dns(domain_name))
+            .map(|list| list.dns_entries)
+    }
+
+    fn dns_entry_insert(&self, domain_name: &str, entry: DnsEntry) -> Result<()> {
+        self.post::<DnsEntryItem>(&self.url.domain_dns(domain_name), entry.into())
+    }
+
+    fn dns_entry_replace_all(&self, domain_name: &str, entries: Vec<DnsEntry>) -> Result<()> {
+        self.put::<DnsEntryList>(&self.url.domain_dns(domain_name), entries.into())
+    }
+
+    fn dns_entry_update(&self, domain_name: &str, entry: DnsEntry) -> Result<()> {
+        self.patch::<DnsEntryItem>(&self.url.domain_dns(domain_name), entry.into())
+    }
+
+    fn dns_entry_upsert(&self, domain_name: &str, entry: DnsEntry) -> Result<()> {
+        match Upsert::new(&self.dns_entry_list(domain_name)?, &entry) {
+            Upsert::Unchanged => Ok(()),
+            Upsert::Insert => self.dns_entry_insert(domain_name, entry),
+            Upsert::Update => self.dns_entry_update(domain_name, entry),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncDnsApi for crate::AsyncClient {
+    async fn dns_entry_delete(&self, domain_name: &str, entry: DnsEntry) -> Result<()> {
+        self.delete::<DnsEntryItem>(&self.url.domain_dns(domain_name), entry.into())
+            .await
+    }
+
+    async fn dns_entry_delete_all<F>(&self, domain_name: &str, f: F) -> Result<()>
+    where
+        F: Fn(&DnsEntry) -> bool + Send,
+    {
+        for dns_entry in self
+            .dns_entry_list(domain_name)
+            .await?
+            .into_iter()
+            .filter(f)
+        {
+            self.dns_entry_delete(domain_name, dns_entry).await?;
+        }
+        Ok(())
+    }
+
+    async fn dns_entry_list(&self, domain_name: &str) -> Result<Vec<DnsEntry>> {
+        self.get::<DnsEntryList>(&self.url.domain_dns(domain_name))
+            .await
+            .map(|list| list.dns_entries)
+    }
+
+    async fn dns_entry_insert(&self, domain_name: &str, entry: DnsEntry) -> Result<()> {
+        self.post::<DnsEntryItem>(&self.url.domain_dns(domain_name), entry.into())
+            .await
+    }
+
+    async fn dns_entry_replace_all(&self, domain_name: &str, entries: Vec<DnsEntry>) -> Result<()> {
+        self.put::<DnsEntryList>(&self.url.domain_dns(domain_name), entries.into())
+            .await
+    }
+
+    async fn dns_entry_update(&self, domain_name: &str, entry: DnsEntry) -> Result<()> {
+        self.patch::<DnsEntryItem>(&self.url.domain_dns(domain_name), entry.into())
+            .await
+    }
+
+    async fn dns_entry_upsert(&self, domain_name: &str, entry: DnsEntry) -> Result<()> {
+        match Upsert::new(&self.dns_entry_list(domain_name).await?, &entry) {
+            Upsert::Unchanged => Ok(()),
+            Upsert::Insert => self.dns_entry_insert(domain_name, entry).await,
+            Upsert::Update => self.dns_entry_update(domain_name, entry).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DnsApi, DnsEntry, RecordType};
+    use crate::transport::{MemoryTransport, Method, Response};
+    use crate::{Client, HasNames};
+
+    fn upsert(entry: &str) -> Vec<Method> {
+        let transport = MemoryTransport::default()
+            .respond(
+                Method::Get,
+                "/domains/example.com/dns",
+                Response::json(
+                    200,
+                    r#"{"dnsEntries":[{"name":"www","expire":300,"type":"A","content":"192.0.2.1"}]}"#,
+                ),
+            )
+            .respond(Method::Post, "/dns", Response::new(201))
+            .respond(Method::Patch, "/dns", Response::new(204));
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone());
+        client
+            .dns_entry_upsert("example.com", entry.parse().unwrap())
+            .unwrap();
+        transport
+            .requests()
+            .iter()
+            .map(|request| request.method)
+            .collect()
+    }
+
+    #[test]
+    fn acme_challenge() {
+        let dns_entry = DnsEntry::new_acme_challenge(60, "Hallo");
+        assert!(dns_entry.is_acme_challenge());
+        let subdomain = "_acme-challenge.www 60 TXT Hallo"
+            .parse::<DnsEntry>()
+            .unwrap();
+        assert!(subdomain.is_acme_challenge());
+        let other = "_acme-challenger 60 TXT Hallo".parse::<DnsEntry>().unwrap();
+        assert!(!other.is_acme_challenge());
+    }
+
+    #[test]
+    fn transip_domain_key() {
+        let entries = Client::fixture("dns")
+            .dns_entry_list("transipdemo.be")
+            .unwrap();
+        let names = entries
+            .iter()
+            .filter(|entry| entry.is_transip_domain_key())
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "transip-A._domainkey",
+                "transip-B._domainkey",
+                "transip-C._domainkey"
+            ]
+        );
+    }
+
+    #[test]
+    fn record_types() {
+        assert_eq!(RecordType::A.to_string().as_str(), "A");
+        assert_eq!(RecordType::AAAA.to_string().as_str(), "AAAA");
+        assert_eq!(RecordType::ALIAS.to_string().as_str(), "ALIAS");
+        assert_eq!(RecordType::CNAME.to_string().as_str(), "CNAME");
+        assert_eq!(RecordType::MX.to_string().as_str(), "MX");
+        assert_eq!(RecordType::NS.to_string().as_str(), "NS");
+        assert_eq!(RecordType::PTR.to_string().as_str(), "PTR");
+        assert_eq!(RecordType::SOA.to_string().as_str(), "SOA");
+        assert_eq!(RecordType::SRV.to_string().as_str(), "SRV");
+        assert_eq!(RecordType::TXT.to_string().as_str(), "TXT");
+        assert_eq!(RecordType::CAA.to_string().as_str(), "CAA");
+        assert_eq!(RecordType::DS.to_string().as_str(), "DS");
+        assert_eq!(RecordType::NAPTR.to_string().as_str(), "NAPTR");
+        assert_eq!(RecordType::SSHFP.to_string().as_str(), "SSHFP");
+        assert_eq!(RecordType::TLSA.to_string().as_str(), "TLSA");
+    }
+
+    #[test]
+    fn record_types_from_str() {
+        assert_eq!("A".parse::<RecordType>().unwrap(), RecordType::A);
+        assert_eq!("AAAA".parse::<RecordType>().unwrap(), RecordType::AAAA);
+        assert_eq!("ALIAS".parse::<RecordType>().unwrap(), RecordType::ALIAS);
+        assert_eq!("CNAME".parse::<RecordType>().unwrap(), RecordType::CNAME);
+        assert_eq!("MX".parse::<RecordType>().unwrap(), RecordType::MX);
+        assert_eq!("NS".parse::<RecordType>().unwrap(), RecordType::NS);
+        assert_eq!("PTR".parse::<RecordType>().unwrap(), RecordType::PTR);
+        assert_eq!("SOA".parse::<RecordType>().unwrap(), RecordType::SOA);
+        assert_eq!("SRV".parse::<RecordType>().unwrap(), RecordType::SRV);
+        assert_eq!("TXT".parse::<RecordType>().unwrap(), RecordType::TXT);
+        assert_eq!("CAA".parse::<RecordType>().unwrap(), RecordType::CAA);
+        assert_eq!("DS".parse::<RecordType>().unwrap(), RecordType::DS);
+        assert_eq!("NAPTR".parse::<RecordType>().unwrap(), RecordType::NAPTR);
+        assert_eq!("SSHFP".parse::<RecordType>().unwrap(), RecordType::SSHFP);
+        assert_eq!("TLSA".parse::<RecordType>().unwrap(), RecordType::TLSA);
+    }
+
+    #[test]
+    fn domain_entry_from_str() {
+        assert_eq!(
+            "www 30 A 235.4.3.231".parse::<DnsEntry>().unwrap(),
+            DnsEntry {
+                name: "www".to_owned(),
+                expire: 30,
+                entry_type: "A".to_owned(),
+                content: "235.4.3.231".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            "_acme-challenge 60 TXT Er is een kindeke"
+                .parse::<DnsEntry>()
+                .unwrap(),
+            DnsEntry {
+                name: "_acme-challenge".to_owned(),
+                expire: 60,
+                entry_type: "TXT".to_owned(),
+                content: "Er is een kindeke".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn domain_entry_list() {
+        let entry_list = Client::fixture("dns")
+            .dns_entry_list("transipdemo.be")
+            .unwrap();
+        let names = entry_list.names();
+
+        assert_eq!(
+            names,
+            vec![
+                "*",
+                "*",
+                "@",
+                "@",
+                "@",
+                "@",
+                "transip-A._domainkey",
+                "transip-B._domainkey",
+                "transip-C._domainkey",
+                "_dmarc",
+            ],
+        );
+    }
+
+    #[test]
+    fn dns_entry_update() {
+        let transport = MemoryTransport::default().respond(
+            Method::Patch,
+            "/domains/example.com/dns",
+            Response::new(204),
+        );
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone());
+        client
+            .dns_entry_update("example.com", "www 300 A 192.0.2.2".parse().unwrap())
+            .unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::Patch);
+        assert_eq!(
+            requests[0].body.as_deref(),
+            Some(
+                br#"{"dnsEntry":{"name":"www","expire":300,"type":"A","content":"192.0.2.2"}}"#
+                    .as_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn dns_entry_upsert() {
+        assert_eq!(upsert("www 300 A 192.0.2.1"), vec![Method::Get]);
+        assert_eq!(
+            upsert("www 300 A 192.0.2.2"),
+            vec![Method::Get, Method::Patch]
+        );
+        assert_eq!(
+            upsert("www 3600 A 192.0.2.2"),
+            vec![Method::Get, Method::Post]
+        );
+        assert_eq!(
+            upsert("mail 300 A 192.0.2.2"),
+            vec![Method::Get, Method::Post]
+        );
+    }
+}
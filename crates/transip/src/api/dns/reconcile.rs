@@ -0,0 +1,373 @@
+use std::fmt::Display;
+
+use super::{DnsApi, DnsEntry};
+use crate::Result;
+
+/// A single change of a [`Plan`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Add(DnsEntry),
+    Remove(DnsEntry),
+    /// An entry gets other content, in place with [`DnsApi::dns_entry_update`]
+    Update {
+        from: DnsEntry,
+        to: DnsEntry,
+    },
+    /// An entry with the same name and type gets another ttl or content,
+    /// but can not be updated in place, so it is removed and inserted
+    Replace {
+        from: DnsEntry,
+        to: DnsEntry,
+    },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Add(entry) => write!(f, "+ {entry}"),
+            Change::Remove(entry) => write!(f, "- {entry}"),
+            Change::Update { from, to } => write!(f, "~ {from} -> {to}"),
+            Change::Replace { from, to } => write!(f, "-/+ {from} -> {to}"),
+        }
+    }
+}
+
+/// The changes that turn the current entries of a domain into the desired entries.
+///
+/// Create a plan with [`Plan::new`], show it for review and [`apply`](Plan::apply) it.
+///
+/// Example
+/// ```
+/// use transip::api::dns::{DnsEntry, Plan};
+///
+/// let current = vec![
+///     "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap(),
+///     "old 300 A 192.0.2.9".parse::<DnsEntry>().unwrap(),
+///     "transip-A._domainkey 3600 CNAME _dkim-A.transip.email.".parse::<DnsEntry>().unwrap(),
+/// ];
+/// let desired = vec![
+///     "www 3600 A 192.0.2.1".parse::<DnsEntry>().unwrap(),
+///     "mail 300 A 192.0.2.2".parse::<DnsEntry>().unwrap(),
+/// ];
+/// let plan = Plan::new("example.com", &current, &desired, DnsEntry::is_transip_domain_key);
+/// assert_eq!(
+///     plan.to_string(),
+///     "+ mail 300 A 192.0.2.2\n\
+///      -/+ www 300 A 192.0.2.1 -> www 3600 A 192.0.2.1\n\
+///      - old 300 A 192.0.2.9\n"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    pub domain_name: String,
+    /// Additions first, then updates and removals last
+    pub changes: Vec<Change>,
+}
+
+/// Removes and returns the first entry that matches
+fn take<F>(entries: &mut Vec<DnsEntry>, f: F) -> Option<DnsEntry>
+where
+    F: Fn(&DnsEntry) -> bool,
+{
+    entries
+        .iter()
+        .position(f)
+        .map(|index| entries.remove(index))
+}
+
+impl Plan {
+    /// Compares the current entries with the desired entries.
+    ///
+    /// Entries that are equal stay as they are.
+    /// An entry with the same name, type and content, or else the same name and type,
+    /// is updated. The remaining entries are added or removed.
+    /// Only the content of an entry can be updated in place, and only if no other entry,
+    /// current or desired, has the same name, type and ttl. Other updates are a [`Change::Replace`].
+    /// Current entries for which `protected` returns true are never updated or removed,
+    /// desired entries with the same name and type as a protected entry are left out.
+    pub fn new<F>(
+        domain_name: &str,
+        current: &[DnsEntry],
+        desired: &[DnsEntry],
+        protected: F,
+    ) -> Self
+    where
+        F: Fn(&DnsEntry) -> bool,
+    {
+        let existing = current;
+        let mut current = current.to_vec();
+        let mut desired = desired
+            .iter()
+            .filter(|entry| {
+                take(&mut current, |existing| {
+                    existing.same_identity(entry) && existing.same_content(entry)
+                })
+                .is_none()
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        // A protected entry stays as it is, so a desired entry with its name and type
+        // would be a conflicting second entry
+        let (kept, unprotected) = current
+            .into_iter()
+            .partition::<Vec<_>, _>(|entry| protected(entry));
+        current = unprotected;
+        desired.retain(|entry| {
+            !kept
+                .iter()
+                .any(|kept| kept.name == entry.name && kept.entry_type == entry.entry_type)
+        });
+
+        let mut updates = vec![];
+        for same_content in [true, false] {
+            let mut unmatched = vec![];
+            for entry in desired {
+                let from = take(&mut current, |existing| {
+                    existing.name == entry.name
+                        && existing.entry_type == entry.entry_type
+                        && (!same_content || existing.same_content(&entry))
+                });
+                match from {
+                    Some(from) => updates.push((from, entry)),
+                    None => unmatched.push(entry),
+                }
+            }
+            desired = unmatched;
+        }
+        // Updates are applied after the additions, before the removals.
+        // The api matches an update on its identity, so while the plan is applied
+        // no other entry may have that identity, not even one that another change inserts.
+        // Besides `from` itself, only the `to` of its own update may be counted.
+        let in_place = |from: &DnsEntry, to: &DnsEntry| {
+            from.same_identity(to)
+                && existing
+                    .iter()
+                    .chain(&desired)
+                    .chain(updates.iter().map(|(_, to)| to))
+                    .filter(|entry| entry.same_identity(from))
+                    .count()
+                    == 2
+        };
+        let updates = updates
+            .iter()
+            .map(|(from, to)| match in_place(from, to) {
+                true => Change::Update {
+                    from: from.clone(),
+                    to: to.clone(),
+                },
+                false => Change::Replace {
+                    from: from.clone(),
+                    to: to.clone(),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            domain_name: domain_name.to_owned(),
+            changes: desired
+                .into_iter()
+                .map(Change::Add)
+                .chain(updates)
+                .chain(current.into_iter().map(Change::Remove))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Carries out the changes, in the order of the plan
+    pub fn apply<A: DnsApi>(&self, api: &A) -> Result<()> {
+        for change in &self.changes {
+            match change {
+                Change::Add(entry) => api.dns_entry_insert(&self.domain_name, entry.clone())?,
+                Change::Remove(entry) => api.dns_entry_delete(&self.domain_name, entry.clone())?,
+                Change::Update { to, .. } => api.dns_entry_update(&self.domain_name, to.clone())?,
+                Change::Replace { from, to } => {
+                    api.dns_entry_delete(&self.domain_name, from.clone())?;
+                    api.dns_entry_insert(&self.domain_name, to.clone())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Async version of [`apply`](Plan::apply)
+    #[cfg(feature = "async")]
+    pub async fn apply_async<A: super::AsyncDnsApi + Sync>(&self, api: &A) -> Result<()> {
+        for change in &self.changes {
+            match change {
+                Change::Add(entry) => {
+                    api.dns_entry_insert(&self.domain_name, entry.clone())
+                        .await?
+                }
+                Change::Remove(entry) => {
+                    api.dns_entry_delete(&self.domain_name, entry.clone())
+                        .await?
+                }
+                Change::Update { to, .. } => {
+                    api.dns_entry_update(&self.domain_name, to.clone()).await?
+                }
+                Change::Replace { from, to } => {
+                    api.dns_entry_delete(&self.domain_name, from.clone())
+                        .await?;
+                    api.dns_entry_insert(&self.domain_name, to.clone()).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One change per line
+impl Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Change, Plan};
+    use crate::api::dns::DnsEntry;
+    use crate::client::Client;
+    use crate::transport::{MemoryTransport, Method, Response};
+
+    fn entries(lines: &[&str]) -> Vec<DnsEntry> {
+        lines.iter().map(|line| line.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn no_changes() {
+        let current = entries(&["www 300 A 192.0.2.1", "www 300 A 192.0.2.2"]);
+        let desired = entries(&["www 300 A 192.0.2.2", "www 300 A 192.0.2.1"]);
+        assert!(Plan::new("example.com", &current, &desired, |_| false).is_empty());
+    }
+
+    #[test]
+    fn unquoted_content() {
+        let current = entries(&[r#"@ 3600 CAA 0 issue "letsencrypt.org""#]);
+        let desired = entries(&["@ 3600 CAA 0 issue letsencrypt.org"]);
+        assert!(Plan::new("example.com", &current, &desired, |_| false).is_empty());
+        assert!(Plan::new("example.com", &desired, &current, |_| false).is_empty());
+    }
+
+    #[test]
+    fn changes() {
+        let current = entries(&[
+            "www 300 A 192.0.2.1",
+            "www 300 A 192.0.2.2",
+            "@ 300 MX 10 mail",
+            "old 300 CNAME www",
+        ]);
+        let desired = entries(&[
+            "www 300 A 192.0.2.1",
+            "www 3600 A 192.0.2.2",
+            "@ 300 MX 20 mail",
+            "@ 300 TXT v=spf1 -all",
+        ]);
+        let plan = Plan::new("example.com", &current, &desired, |_| false);
+        assert_eq!(
+            plan.changes,
+            vec![
+                Change::Add(desired[3].clone()),
+                Change::Replace {
+                    from: current[1].clone(),
+                    to: desired[1].clone()
+                },
+                Change::Update {
+                    from: current[2].clone(),
+                    to: desired[2].clone()
+                },
+                Change::Remove(current[3].clone()),
+            ]
+        );
+    }
+
+    #[test]
+    fn update_in_place() {
+        let current = entries(&["www 300 A 192.0.2.1", "@ 300 A 192.0.2.1"]);
+        let desired = entries(&[
+            "www 300 A 192.0.2.2",
+            "@ 300 A 192.0.2.2",
+            "@ 300 A 192.0.2.3",
+        ]);
+        let plan = Plan::new("example.com", &current, &desired, |_| false);
+        assert_eq!(
+            plan.to_string(),
+            "+ @ 300 A 192.0.2.3\n\
+             ~ www 300 A 192.0.2.1 -> www 300 A 192.0.2.2\n\
+             -/+ @ 300 A 192.0.2.1 -> @ 300 A 192.0.2.2\n"
+        );
+    }
+
+    #[test]
+    fn replace_into_updated_identity() {
+        let current = entries(&["www 3600 A 192.0.2.2", "www 300 A 192.0.2.1"]);
+        let desired = entries(&["www 300 A 192.0.2.4", "www 300 A 192.0.2.3"]);
+        let plan = Plan::new("example.com", &current, &desired, |_| false);
+        assert_eq!(
+            plan.to_string(),
+            "-/+ www 3600 A 192.0.2.2 -> www 300 A 192.0.2.4\n\
+             -/+ www 300 A 192.0.2.1 -> www 300 A 192.0.2.3\n"
+        );
+    }
+
+    #[test]
+    fn protected() {
+        let current = entries(&[
+            "transip-A._domainkey 3600 CNAME _dkim-A.transip.email.",
+            "www 300 A 192.0.2.1",
+        ]);
+        let desired = entries(&["transip-A._domainkey 3600 CNAME other.example.com."]);
+        let plan = Plan::new(
+            "example.com",
+            &current,
+            &desired,
+            DnsEntry::is_transip_domain_key,
+        );
+        assert_eq!(plan.to_string(), "- www 300 A 192.0.2.1\n");
+    }
+
+    #[test]
+    fn apply() {
+        let transport = MemoryTransport::default()
+            .respond(Method::Post, "/dns", Response::new(201))
+            .respond(Method::Patch, "/dns", Response::new(204))
+            .respond(Method::Delete, "/dns", Response::new(204));
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone());
+        let current = entries(&[
+            "www 300 A 192.0.2.1",
+            "old 300 A 192.0.2.9",
+            "@ 300 MX 10 mail",
+        ]);
+        let desired = entries(&[
+            "www 3600 A 192.0.2.1",
+            "new 300 A 192.0.2.2",
+            "@ 300 MX 20 mail",
+        ]);
+        Plan::new("example.com", &current, &desired, |_| false)
+            .apply(&client)
+            .unwrap();
+
+        let methods = transport
+            .requests()
+            .iter()
+            .map(|request| request.method)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            methods,
+            vec![
+                Method::Post,
+                Method::Delete,
+                Method::Post,
+                Method::Patch,
+                Method::Delete
+            ]
+        );
+    }
+}
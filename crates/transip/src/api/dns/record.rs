@@ -0,0 +1,578 @@
+use std::fmt::Display;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use super::{DnsEntry, RecordType};
+use crate::{Error, Result};
+
+/// Typed content of a [`DnsEntry`].
+///
+/// Converting an entry checks the content for its record type,
+/// so an invalid entry is caught before it is sent to the api.
+/// The content of a record converts back to a [`DnsEntry`] with the same record.
+/// Values that are written between quotes are always quoted, so compare entries
+/// with [`DnsEntry::same_content`] instead of comparing the content as text.
+///
+/// Example
+/// ```
+/// use transip::api::dns::{DnsEntry, DnsRecord};
+///
+/// let record = DnsRecord::MX {
+///     priority: 10,
+///     exchange: "mail.example.com.".to_owned(),
+/// };
+/// let entry = DnsEntry::new("@", 3600, record.clone());
+/// assert_eq!(entry.to_string(), "@ 3600 MX 10 mail.example.com.");
+/// assert_eq!(entry.record().unwrap(), record);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DnsRecord {
+    A(Ipv4Addr),
+    AAAA(Ipv6Addr),
+    ALIAS(String),
+    /// Certification authority authorization, the value is written between quotes
+    CAA {
+        flags: u8,
+        tag: String,
+        value: String,
+    },
+    CNAME(String),
+    /// Delegation signer, with a hexadecimal digest
+    DS {
+        key_tag: u16,
+        algorithm: u8,
+        digest_type: u8,
+        digest: String,
+    },
+    MX {
+        priority: u16,
+        exchange: String,
+    },
+    /// Naming authority pointer, flags, service and regexp are written between quotes
+    NAPTR {
+        order: u16,
+        preference: u16,
+        flags: String,
+        service: String,
+        regexp: String,
+        replacement: String,
+    },
+    NS(String),
+    PTR(String),
+    SOA {
+        mname: String,
+        rname: String,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    },
+    SRV {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+    /// Fingerprint of a ssh host key, in hexadecimal
+    SSHFP {
+        algorithm: u8,
+        fingerprint_type: u8,
+        fingerprint: String,
+    },
+    /// Certificate association of a tls service, in hexadecimal
+    TLSA {
+        usage: u8,
+        selector: u8,
+        matching_type: u8,
+        data: String,
+    },
+    TXT(String),
+}
+
+/// A host name is a single word, like `mail.example.com.` or `@`
+fn host(content: &str) -> Result<String> {
+    if content.is_empty() || content.contains(char::is_whitespace) {
+        Err(Error::ParseDnsEntry("invalid host name"))
+    } else {
+        Ok(content.to_owned())
+    }
+}
+
+/// Splits the content in whitespace separated words.
+/// A word between double quotes can contain whitespace, the quotes are removed.
+fn words(content: &str) -> Result<Vec<&str>> {
+    let mut words = vec![];
+    let mut rest = content.trim_start();
+    while !rest.is_empty() {
+        let (word, remainder) = match rest.strip_prefix('"') {
+            Some(quoted) => quoted
+                .split_once('"')
+                .ok_or(Error::ParseDnsEntry("closing quote missing"))?,
+            None => rest.split_at(rest.find(char::is_whitespace).unwrap_or(rest.len())),
+        };
+        words.push(word);
+        rest = remainder.trim_start();
+    }
+    Ok(words)
+}
+
+/// Splits the content in exactly `N` words
+fn fields<'a, const N: usize>(content: &'a str, error: &'static str) -> Result<[&'a str; N]> {
+    words(content)?
+        .try_into()
+        .map_err(|_| Error::ParseDnsEntry(error))
+}
+
+/// A number that is one of the values defined for a field
+fn code(field: &str, valid: &[u8], error: &'static str) -> Result<u8> {
+    number(field, error).and_then(|code| {
+        valid
+            .contains(&code)
+            .then_some(code)
+            .ok_or(Error::ParseDnsEntry(error))
+    })
+}
+
+/// Hexadecimal data, with the number of digits that the digest algorithm produces.
+/// The length is not checked if it is unknown.
+fn hex(field: &str, length: Option<usize>, error: &'static str) -> Result<String> {
+    let valid = !field.is_empty()
+        && field.len().is_multiple_of(2)
+        && field.bytes().all(|byte| byte.is_ascii_hexdigit())
+        && length.is_none_or(|length| field.len() == length);
+    valid
+        .then(|| field.to_owned())
+        .ok_or(Error::ParseDnsEntry(error))
+}
+
+/// Number of hexadecimal digits of a SHA-1, SHA-256 or SHA-384 digest
+fn digest_length(digest_type: u8) -> Option<usize> {
+    match digest_type {
+        1 => Some(40),
+        2 => Some(64),
+        4 => Some(96),
+        _ => None,
+    }
+}
+
+/// Text that is written between double quotes, so it can not contain a double quote
+fn quotable(text: &str, error: &'static str) -> Result<String> {
+    if text.contains('"') {
+        Err(Error::ParseDnsEntry(error))
+    } else {
+        Ok(text.to_owned())
+    }
+}
+
+fn number<T: FromStr>(field: &str, error: &'static str) -> Result<T> {
+    field.parse::<T>().map_err(|_| Error::ParseDnsEntry(error))
+}
+
+impl DnsRecord {
+    /// Parse the content of an entry with this record type
+    pub fn parse(record_type: RecordType, content: &str) -> Result<Self> {
+        let content = content.trim();
+        if content.is_empty() {
+            return Err(Error::ParseDnsEntry("content missing"));
+        }
+        match record_type {
+            RecordType::A => content
+                .parse()
+                .map(DnsRecord::A)
+                .map_err(|_| Error::ParseDnsEntry("invalid IPv4 address")),
+            RecordType::AAAA => content
+                .parse()
+                .map(DnsRecord::AAAA)
+                .map_err(|_| Error::ParseDnsEntry("invalid IPv6 address")),
+            RecordType::ALIAS => host(content).map(DnsRecord::ALIAS),
+            RecordType::CAA => {
+                const ERROR: &str = "CAA needs flags, a tag and a value";
+                let [flags, tag, value] = fields(content, ERROR)?;
+                if tag.is_empty() || !tag.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+                    return Err(Error::ParseDnsEntry("CAA tag should be alphanumeric"));
+                }
+                Ok(DnsRecord::CAA {
+                    flags: number(flags, ERROR)?,
+                    tag: tag.to_owned(),
+                    value: quotable(value, ERROR)?,
+                })
+            }
+            RecordType::CNAME => host(content).map(DnsRecord::CNAME),
+            RecordType::DS => {
+                const ERROR: &str = "DS needs a key tag, algorithm, digest type and digest";
+                let [key_tag, algorithm, digest_type, digest] = fields(content, ERROR)?;
+                let digest_type = number(digest_type, ERROR)?;
+                Ok(DnsRecord::DS {
+                    key_tag: number(key_tag, ERROR)?,
+                    algorithm: number(algorithm, ERROR)?,
+                    digest_type,
+                    digest: hex(digest, digest_length(digest_type), "invalid DS digest")?,
+                })
+            }
+            RecordType::MX => {
+                const ERROR: &str = "MX needs a priority and an exchange";
+                let [priority, exchange] = fields(content, ERROR)?;
+                Ok(DnsRecord::MX {
+                    priority: number(priority, ERROR)?,
+                    exchange: host(exchange)?,
+                })
+            }
+            RecordType::NAPTR => {
+                const ERROR: &str =
+                    "NAPTR needs an order, preference, flags, service, regexp and replacement";
+                let [order, preference, flags, service, regexp, replacement] =
+                    fields(content, ERROR)?;
+                Ok(DnsRecord::NAPTR {
+                    order: number(order, ERROR)?,
+                    preference: number(preference, ERROR)?,
+                    flags: quotable(flags, ERROR)?,
+                    service: quotable(service, ERROR)?,
+                    regexp: quotable(regexp, ERROR)?,
+                    replacement: host(replacement)?,
+                })
+            }
+            RecordType::NS => host(content).map(DnsRecord::NS),
+            RecordType::PTR => host(content).map(DnsRecord::PTR),
+            RecordType::SOA => {
+                const ERROR: &str =
+                    "SOA needs mname, rname, serial, refresh, retry, expire and minimum";
+                let [mname, rname, serial, refresh, retry, expire, minimum] =
+                    fields(content, ERROR)?;
+                Ok(DnsRecord::SOA {
+                    mname: host(mname)?,
+                    rname: host(rname)?,
+                    serial: number(serial, ERROR)?,
+                    refresh: number(refresh, ERROR)?,
+                    retry: number(retry, ERROR)?,
+                    expire: number(expire, ERROR)?,
+                    minimum: number(minimum, ERROR)?,
+                })
+            }
+            RecordType::SRV => {
+                const ERROR: &str = "SRV needs a priority, weight, port and target";
+                let [priority, weight, port, target] = fields(content, ERROR)?;
+                Ok(DnsRecord::SRV {
+                    priority: number(priority, ERROR)?,
+                    weight: number(weight, ERROR)?,
+                    port: number(port, ERROR)?,
+                    target: host(target)?,
+                })
+            }
+            RecordType::SSHFP => {
+                const ERROR: &str = "SSHFP needs an algorithm, fingerprint type and fingerprint";
+                let [algorithm, fingerprint_type, fingerprint] = fields(content, ERROR)?;
+                let fingerprint_type = code(fingerprint_type, &[1, 2], ERROR)?;
+                Ok(DnsRecord::SSHFP {
+                    algorithm: code(algorithm, &[1, 2, 3, 4, 6], ERROR)?,
+                    fingerprint_type,
+                    fingerprint: hex(
+                        fingerprint,
+                        digest_length(fingerprint_type),
+                        "invalid SSHFP fingerprint",
+                    )?,
+                })
+            }
+            RecordType::TLSA => {
+                const ERROR: &str = "TLSA needs a usage, selector, matching type and data";
+                let [usage, selector, matching_type, data] = fields(content, ERROR)?;
+                let matching_type = code(matching_type, &[0, 1, 2], ERROR)?;
+                let length = match matching_type {
+                    1 => Some(64),
+                    2 => Some(128),
+                    _ => None,
+                };
+                Ok(DnsRecord::TLSA {
+                    usage: code(usage, &[0, 1, 2, 3], ERROR)?,
+                    selector: code(selector, &[0, 1], ERROR)?,
+                    matching_type,
+                    data: hex(data, length, "invalid TLSA data")?,
+                })
+            }
+            RecordType::TXT => Ok(DnsRecord::TXT(content.to_owned())),
+        }
+    }
+
+    pub fn record_type(&self) -> RecordType {
+        match self {
+            DnsRecord::A(_) => RecordType::A,
+            DnsRecord::AAAA(_) => RecordType::AAAA,
+            DnsRecord::ALIAS(_) => RecordType::ALIAS,
+            DnsRecord::CAA { .. } => RecordType::CAA,
+            DnsRecord::CNAME(_) => RecordType::CNAME,
+            DnsRecord::DS { .. } => RecordType::DS,
+            DnsRecord::MX { .. } => RecordType::MX,
+            DnsRecord::NAPTR { .. } => RecordType::NAPTR,
+            DnsRecord::NS(_) => RecordType::NS,
+            DnsRecord::PTR(_) => RecordType::PTR,
+            DnsRecord::SOA { .. } => RecordType::SOA,
+            DnsRecord::SRV { .. } => RecordType::SRV,
+            DnsRecord::SSHFP { .. } => RecordType::SSHFP,
+            DnsRecord::TLSA { .. } => RecordType::TLSA,
+            DnsRecord::TXT(_) => RecordType::TXT,
+        }
+    }
+}
+
+/// The content of the record, as used in [`DnsEntry::content`]
+impl Display for DnsRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DnsRecord::A(address) => write!(f, "{address}"),
+            DnsRecord::AAAA(address) => write!(f, "{address}"),
+            DnsRecord::ALIAS(host)
+            | DnsRecord::CNAME(host)
+            | DnsRecord::NS(host)
+            | DnsRecord::PTR(host) => write!(f, "{host}"),
+            DnsRecord::CAA { flags, tag, value } => write!(f, r#"{flags} {tag} "{value}""#),
+            DnsRecord::DS {
+                key_tag,
+                algorithm,
+                digest_type,
+                digest,
+            } => write!(f, "{key_tag} {algorithm} {digest_type} {digest}"),
+            DnsRecord::MX { priority, exchange } => write!(f, "{priority} {exchange}"),
+            DnsRecord::NAPTR {
+                order,
+                preference,
+                flags,
+                service,
+                regexp,
+                replacement,
+            } => write!(
+                f,
+                r#"{order} {preference} "{flags}" "{service}" "{regexp}" {replacement}"#
+            ),
+            DnsRecord::SOA {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => write!(
+                f,
+                "{mname} {rname} {serial} {refresh} {retry} {expire} {minimum}"
+            ),
+            DnsRecord::SRV {
+                priority,
+                weight,
+                port,
+                target,
+            } => write!(f, "{priority} {weight} {port} {target}"),
+            DnsRecord::SSHFP {
+                algorithm,
+                fingerprint_type,
+                fingerprint,
+            } => write!(f, "{algorithm} {fingerprint_type} {fingerprint}"),
+            DnsRecord::TLSA {
+                usage,
+                selector,
+                matching_type,
+                data,
+            } => write!(f, "{usage} {selector} {matching_type} {data}"),
+            DnsRecord::TXT(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+impl TryFrom<&DnsEntry> for DnsRecord {
+    type Error = Error;
+    fn try_from(entry: &DnsEntry) -> Result<Self> {
+        entry.record()
+    }
+}
+
+impl TryFrom<DnsEntry> for DnsRecord {
+    type Error = Error;
+    fn try_from(entry: DnsEntry) -> Result<Self> {
+        entry.record()
+    }
+}
+
+impl DnsEntry {
+    pub fn new<S: Into<String>>(name: S, expire: u32, record: DnsRecord) -> Self {
+        Self {
+            name: name.into(),
+            expire,
+            entry_type: record.record_type().to_string(),
+            content: record.to_string(),
+        }
+    }
+
+    pub fn record_type(&self) -> Result<RecordType> {
+        self.entry_type
+            .parse::<RecordType>()
+            .map_err(|_| Error::ParseDnsEntry("unknown record type"))
+    }
+
+    /// The typed content of the entry, checked for its record type
+    pub fn record(&self) -> Result<DnsRecord> {
+        DnsRecord::parse(self.record_type()?, &self.content)
+    }
+
+    /// Both entries have the same type and record, so `0 issue letsencrypt.org`
+    /// and `0 issue "letsencrypt.org"` are the same CAA content.
+    /// Content that is not a valid record is compared as written.
+    pub fn same_content(&self, other: &DnsEntry) -> bool {
+        self.entry_type == other.entry_type
+            && match (self.record(), other.record()) {
+                (Ok(record), Ok(other)) => record == other,
+                _ => self.content == other.content,
+            }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use super::DnsRecord;
+    use crate::Error;
+    use crate::api::dns::{DnsEntry, RecordType};
+
+    fn parse_error(record_type: RecordType, content: &str) -> &'static str {
+        match DnsRecord::parse(record_type, content) {
+            Err(Error::ParseDnsEntry(message)) => message,
+            Err(error) => panic!("Unexpected error {error}"),
+            Ok(record) => panic!("Record {record:?} accepted"),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for line in [
+            "www 300 A 192.0.2.1",
+            "www 300 AAAA 2a01:7c8:3:1337::27",
+            "@ 300 ALIAS example.net.",
+            "ftp 300 CNAME www",
+            "@ 3600 MX 10 @",
+            "@ 86400 NS ns0.transip.net.",
+            "1 300 PTR host.example.com.",
+            "@ 3600 SOA ns0.transip.net. hostmaster.transip.nl. 2024010101 14400 3600 604800 300",
+            "_sip._tcp 300 SRV 10 60 5060 sip.example.com.",
+            "@ 300 TXT v=spf1 include:_spf.transip.email ~all",
+            r#"@ 3600 CAA 0 issue "letsencrypt.org""#,
+            r#"@ 3600 CAA 128 iodef "mailto:security@example.com""#,
+            "@ 3600 DS 12345 13 2 3AB0E2F0C9A6C8B4A7E4F1D2C3B4A5968778695A4B3C2D1E0F1A2B3C4D5E6F70",
+            r#"@ 3600 NAPTR 100 10 "S" "SIP+D2U" "" _sip._udp.example.com."#,
+            "@ 3600 SSHFP 4 2 9d97e98f8af710c7e7fe703abc8f639e0ee507c4865fac8d4b0f7c2e9d7bfcd0",
+            "_443._tcp 3600 TLSA 3 1 1 0b9fa5a59eed715c26c1020c711b4f6ec42d58b0015e14337a39dad301c5afc3",
+        ] {
+            let entry = line.parse::<DnsEntry>().unwrap();
+            let record = DnsRecord::try_from(&entry).unwrap();
+            assert_eq!(DnsEntry::new(&entry.name, entry.expire, record), entry);
+        }
+    }
+
+    #[test]
+    fn typed_content() {
+        assert_eq!(
+            "www 300 A 192.0.2.1"
+                .parse::<DnsEntry>()
+                .unwrap()
+                .record()
+                .unwrap(),
+            DnsRecord::A(Ipv4Addr::new(192, 0, 2, 1))
+        );
+        assert_eq!(
+            DnsRecord::parse(RecordType::SRV, "10 60 5060 sip.example.com.").unwrap(),
+            DnsRecord::SRV {
+                priority: 10,
+                weight: 60,
+                port: 5060,
+                target: "sip.example.com.".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn quoted_content() {
+        assert_eq!(
+            DnsRecord::parse(RecordType::CAA, "0 issue letsencrypt.org").unwrap(),
+            DnsRecord::CAA {
+                flags: 0,
+                tag: "issue".to_owned(),
+                value: "letsencrypt.org".to_owned()
+            }
+        );
+        assert_eq!(
+            DnsRecord::parse(RecordType::CAA, r#"0 iodef "mailto:security@example.com""#)
+                .unwrap()
+                .to_string(),
+            r#"0 iodef "mailto:security@example.com""#
+        );
+    }
+
+    #[test]
+    fn unquoted_round_trip() {
+        let entry = "@ 3600 CAA 0 issue letsencrypt.org"
+            .parse::<DnsEntry>()
+            .unwrap();
+        let record = entry.record().unwrap();
+        let converted = DnsEntry::new("@", 3600, record.clone());
+        assert_eq!(converted.content, r#"0 issue "letsencrypt.org""#);
+        assert_eq!(converted.record().unwrap(), record);
+        assert!(converted.same_content(&entry));
+        assert!(entry.same_content(&converted));
+        let other = "@ 3600 CAA 0 issue sectigo.com"
+            .parse::<DnsEntry>()
+            .unwrap();
+        assert!(!other.same_content(&entry));
+    }
+
+    #[test]
+    fn invalid_content() {
+        assert_eq!(
+            parse_error(RecordType::A, "2a01:7c8:3:1337::27"),
+            "invalid IPv4 address"
+        );
+        assert_eq!(
+            parse_error(RecordType::AAAA, "192.0.2.1"),
+            "invalid IPv6 address"
+        );
+        assert_eq!(
+            parse_error(RecordType::MX, "mail.example.com."),
+            "MX needs a priority and an exchange"
+        );
+        assert_eq!(
+            parse_error(RecordType::SRV, "10 60 99999 sip.example.com."),
+            "SRV needs a priority, weight, port and target"
+        );
+        assert_eq!(
+            parse_error(RecordType::CNAME, "two words"),
+            "invalid host name"
+        );
+        assert_eq!(parse_error(RecordType::TXT, " "), "content missing");
+        assert_eq!(
+            parse_error(RecordType::CAA, "0 issue letsencrypt.org extra"),
+            "CAA needs flags, a tag and a value"
+        );
+        assert_eq!(
+            parse_error(RecordType::CAA, r#"0 is-sue "letsencrypt.org""#),
+            "CAA tag should be alphanumeric"
+        );
+        assert_eq!(
+            parse_error(RecordType::CAA, r#"0 issue "letsencrypt.org"#),
+            "closing quote missing"
+        );
+        assert_eq!(
+            parse_error(RecordType::DS, "12345 13 2 3AB0E2F0"),
+            "invalid DS digest"
+        );
+        assert_eq!(
+            parse_error(RecordType::SSHFP, "4 2 not-hex"),
+            "invalid SSHFP fingerprint"
+        );
+        assert_eq!(
+            parse_error(RecordType::TLSA, "4 1 1 0b9fa5a5"),
+            "TLSA needs a usage, selector, matching type and data"
+        );
+        assert_eq!(
+            parse_error(RecordType::TLSA, "3 1 1 0b9fa5a5"),
+            "invalid TLSA data"
+        );
+        assert!("www 300 A localhost".parse::<DnsEntry>().is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use super::{DnsApi, DnsEntry, Plan};
+use crate::{Error, Result};
+
+/// The entries of a domain, saved before they are replaced, so they can be restored.
+///
+/// Example
+/// ```no_run
+/// # #[cfg(feature = "ureq")] {
+/// use transip::api::dns::{DnsEntry, Snapshot};
+/// use transip::{configuration_from_environment, Client};
+///
+/// let client = configuration_from_environment()
+///     .and_then(Client::try_from)
+///     .expect("No cliënt");
+///
+/// let entries = vec!["www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap()];
+/// let snapshot = Snapshot::replace(&client, "example.com", entries).expect("Replace failed");
+/// // Something else went wrong, go back to the old entries
+/// snapshot.restore(&client).expect("Restore failed");
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub domain_name: String,
+    pub entries: Vec<DnsEntry>,
+}
+
+impl Snapshot {
+    /// Save the current entries of the domain
+    pub fn take<A: DnsApi>(api: &A, domain_name: &str) -> Result<Self> {
+        Ok(Self {
+            domain_name: domain_name.to_owned(),
+            entries: api.dns_entry_list(domain_name)?,
+        })
+    }
+
+    /// Replace the entries with the saved entries
+    pub fn restore<A: DnsApi>(&self, api: &A) -> Result<()> {
+        api.dns_entry_replace_all(&self.domain_name, self.entries.clone())
+    }
+
+    /// True if the domain still has the saved entries, in any order
+    pub fn is_current<A: DnsApi>(&self, api: &A) -> Result<bool> {
+        api.dns_entry_list(&self.domain_name).map(|current| {
+            Plan::new(&self.domain_name, &current, &self.entries, |_| false).is_empty()
+        })
+    }
+
+    /// Save the current entries and replace them all in a single request.
+    ///
+    /// If the replacement fails and the entries were changed anyway,
+    /// the saved entries are restored and the error of the replacement is returned.
+    /// If restoring fails as well, [`Error::Rollback`] is returned.
+    /// The snapshot is returned, so the old entries can be restored later.
+    pub fn replace<A: DnsApi>(api: &A, domain_name: &str, entries: Vec<DnsEntry>) -> Result<Self> {
+        let snapshot = Self::take(api, domain_name)?;
+        match api.dns_entry_replace_all(domain_name, entries) {
+            Ok(()) => Ok(snapshot),
+            Err(error) => {
+                if snapshot.is_current(api).unwrap_or_default() {
+                    return Err(error);
+                }
+                tracing::warn!(
+                    "Replacing entries of {} failed with {}, restoring the entries",
+                    domain_name,
+                    error
+                );
+                match snapshot.restore(api) {
+                    Ok(()) => Err(error),
+                    Err(rollback) => Err(Error::Rollback {
+                        error: Box::new(error),
+                        rollback: Box::new(rollback),
+                    }),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::Snapshot;
+    use crate::api::dns::{DnsApi, DnsEntry};
+    use crate::client::Client;
+    use crate::transport::{MemoryTransport, Method, Response};
+    use crate::{Error, Result};
+
+    /// Replacing stores the first entry only and fails,
+    /// restoring fails if `broken` is set.
+    /// Snapshots only list and replace, other calls fail with [`unexpected`].
+    struct Partial {
+        entries: Mutex<Vec<DnsEntry>>,
+        replaced: Mutex<usize>,
+        broken: bool,
+    }
+
+    impl Partial {
+        fn new(entries: &[&str], broken: bool) -> Self {
+            Self {
+                entries: Mutex::new(entries.iter().map(|line| line.parse().unwrap()).collect()),
+                replaced: Mutex::new(0),
+                broken,
+            }
+        }
+    }
+
+    fn unexpected(method: &str) -> Result<()> {
+        Err(Error::Configuration(format!("unexpected call to {method}")))
+    }
+
+    impl DnsApi for Partial {
+        fn dns_entry_delete(&self, _: &str, _: DnsEntry) -> Result<()> {
+            unexpected("dns_entry_delete")
+        }
+
+        fn dns_entry_delete_all<F>(&self, _: &str, _: F) -> Result<()>
+        where
+            F: Fn(&DnsEntry) -> bool,
+        {
+            unexpected("dns_entry_delete_all")
+        }
+
+        fn dns_entry_list(&self, _: &str) -> Result<Vec<DnsEntry>> {
+            Ok(self.entries.lock().unwrap().clone())
+        }
+
+        fn dns_entry_insert(&self, _: &str, _: DnsEntry) -> Result<()> {
+            unexpected("dns_entry_insert")
+        }
+
+        fn dns_entry_update(&self, _: &str, _: DnsEntry) -> Result<()> {
+            unexpected("dns_entry_update")
+        }
+
+        fn dns_entry_upsert(&self, _: &str, _: DnsEntry) -> Result<()> {
+            unexpected("dns_entry_upsert")
+        }
+
+        fn dns_entry_replace_all(&self, _: &str, entries: Vec<DnsEntry>) -> Result<()> {
+            let api_error = |status: u16, message: &str| Error::Api {
+                status,
+                message: message.to_owned(),
+                method: "PUT".to_owned(),
+                url: "dns".to_owned(),
+            };
+            let mut replaced = self.replaced.lock().unwrap();
+            *replaced += 1;
+            if *replaced == 1 {
+                *self.entries.lock().unwrap() = entries.into_iter().take(1).collect();
+                Err(api_error(406, "Invalid entry"))
+            } else if self.broken {
+                Err(api_error(500, "Restore failed"))
+            } else {
+                *self.entries.lock().unwrap() = entries;
+                Ok(())
+            }
+        }
+    }
+
+    fn entries(lines: &[&str]) -> Vec<DnsEntry> {
+        lines.iter().map(|line| line.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn replace() {
+        let transport = MemoryTransport::default()
+            .respond(
+                Method::Get,
+                "/domains/example.com/dns",
+                Response::json(200, r#"{"dnsEntries":[]}"#),
+            )
+            .respond(Method::Put, "/domains/example.com/dns", Response::new(204));
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone());
+        let desired = entries(&["www 300 A 192.0.2.1"]);
+        let snapshot = Snapshot::replace(&client, "example.com", desired).unwrap();
+        assert!(snapshot.entries.is_empty());
+
+        let requests = transport.requests();
+        assert_eq!(requests[1].method, Method::Put);
+        assert_eq!(
+            requests[1].body.as_deref(),
+            Some(
+                br#"{"dnsEntries":[{"name":"www","expire":300,"type":"A","content":"192.0.2.1"}]}"#
+                    .as_slice()
+            )
+        );
+    }
+
+    #[test]
+    fn rollback() {
+        let api = Partial::new(&["www 300 A 192.0.2.1"], false);
+        let desired = entries(&["www 300 A 192.0.2.2", "www 300 A 192.0.2.3"]);
+        let error = Snapshot::replace(&api, "example.com", desired).unwrap_err();
+        assert_eq!(error.status(), Some(406));
+        assert_eq!(
+            api.dns_entry_list("example.com").unwrap(),
+            entries(&["www 300 A 192.0.2.1"])
+        );
+        assert_eq!(*api.replaced.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn rollback_failed() {
+        let api = Partial::new(&["www 300 A 192.0.2.1"], true);
+        let desired = entries(&["www 300 A 192.0.2.2", "www 300 A 192.0.2.3"]);
+        let error = Snapshot::replace(&api, "example.com", desired).unwrap_err();
+        match error {
+            Error::Rollback { error, rollback } => {
+                assert_eq!(error.status(), Some(406));
+                assert_eq!(rollback.status(), Some(500));
+            }
+            error => panic!("Unexpected error {error}"),
+        }
+    }
+
+    #[test]
+    fn unchanged_after_failure() {
+        let api = Partial::new(&["www 300 A 192.0.2.1"], false);
+        let desired = entries(&["www 300 A 192.0.2.1", "www 300 A 192.0.2.3"]);
+        let error = Snapshot::replace(&api, "example.com", desired).unwrap_err();
+        assert_eq!(error.status(), Some(406));
+        assert_eq!(*api.replaced.lock().unwrap(), 1);
+    }
+}
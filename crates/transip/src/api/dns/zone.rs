@@ -0,0 +1,483 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::{DnsEntry, DnsRecord, RecordType};
+use crate::{Error, Result};
+
+/// Maximum length of a single string in the content of a TXT record
+const MAX_STRING_LENGTH: usize = 255;
+const CLASS: &str = "IN";
+
+/// The dns entries of a domain, written as or read from a RFC 1035 zone file.
+///
+/// Names of entries are relative to the domain, like the api uses them.
+///
+/// Example
+/// ```
+/// use transip::api::dns::{DnsEntry, Zone};
+///
+/// let entries = vec![
+///     "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap(),
+///     "@ 3600 TXT v=spf1 -all".parse::<DnsEntry>().unwrap(),
+/// ];
+/// let zone_file = Zone::new("example.com", entries.clone()).to_string();
+/// assert!(zone_file.starts_with("$ORIGIN example.com.\n"));
+/// assert!(zone_file.contains("@\t3600\tIN\tTXT\t\"v=spf1 -all\""));
+/// assert_eq!(Zone::parse("example.com", &zone_file).unwrap().entries, entries);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Zone {
+    /// Name of the domain, without the trailing dot
+    pub domain_name: String,
+    pub entries: Vec<DnsEntry>,
+}
+
+/// A word of a zone file, text between double quotes is a single word
+struct Word {
+    text: String,
+    quoted: bool,
+}
+
+/// A record or directive, which can span multiple lines between parentheses
+#[derive(Default)]
+struct Line {
+    number: usize,
+    /// A record on an indented line has the name of the previous record
+    indented: bool,
+    words: Vec<Word>,
+}
+
+fn zone_error<D: Display>(number: usize, message: D) -> Error {
+    Error::ParseZone(format!("line {number}: {message}"))
+}
+
+/// Reads a quoted string after the opening quote.
+/// A backslash escapes the next character, or is followed by a three digit decimal byte.
+/// The bytes of the text must be valid utf-8.
+fn quoted(chars: &mut Peekable<Chars>, number: usize) -> Result<String> {
+    let mut bytes = vec![];
+    loop {
+        let c = match chars.next() {
+            None | Some('\n') => return Err(zone_error(number, "closing quote missing")),
+            Some('"') => break,
+            Some('\\') => {
+                let digits = chars.clone().take(3).collect::<String>();
+                match digits.parse::<u8>() {
+                    Ok(byte) if digits.len() == 3 && digits.bytes().all(|b| b.is_ascii_digit()) => {
+                        bytes.push(byte);
+                        chars.nth(2);
+                        continue;
+                    }
+                    _ => chars.next(),
+                }
+            }
+            c => c,
+        };
+        if let Some(c) = c {
+            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| zone_error(number, "invalid utf-8 in quoted text"))
+}
+
+/// Splits a zone file in lines of words, without comments and parentheses
+fn lines(zone_file: &str) -> Result<Vec<Line>> {
+    let mut lines = vec![];
+    let mut number = 1;
+    let mut line = Line {
+        number,
+        indented: false,
+        words: vec![],
+    };
+    let mut open = false;
+    let mut line_start = true;
+    let mut chars = zone_file.chars().peekable();
+    while let Some(c) = chars.next() {
+        if line_start && !open {
+            line = Line {
+                number,
+                indented: c == ' ' || c == '\t',
+                words: vec![],
+            };
+        }
+        line_start = false;
+        match c {
+            '\n' => {
+                number += 1;
+                line_start = true;
+                if !open && !line.words.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+            }
+            ';' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '(' if open => return Err(zone_error(number, "nested parentheses")),
+            '(' => open = true,
+            ')' if !open => return Err(zone_error(number, "opening parenthesis missing")),
+            ')' => open = false,
+            '"' => line.words.push(Word {
+                text: quoted(&mut chars, number)?,
+                quoted: true,
+            }),
+            c if c.is_whitespace() => {}
+            c => {
+                let mut text = String::from(c);
+                while let Some(c) =
+                    chars.next_if(|&c| !c.is_whitespace() && !matches!(c, ';' | '(' | ')' | '"'))
+                {
+                    text.push(c);
+                }
+                line.words.push(Word {
+                    text,
+                    quoted: false,
+                });
+            }
+        }
+    }
+    if open {
+        return Err(zone_error(number, "closing parenthesis missing"));
+    }
+    if !line.words.is_empty() {
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+/// Time to live in seconds, or with units like `1h30m`
+fn ttl(text: &str) -> Option<u32> {
+    if text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return text.parse().ok();
+    }
+    let mut seconds = 0u32;
+    let mut value = 0u32;
+    let mut digits = false;
+    for c in text.chars() {
+        match c.to_ascii_lowercase() {
+            '0'..='9' => {
+                value = value.checked_mul(10)?.checked_add(c.to_digit(10)?)?;
+                digits = true;
+            }
+            unit if digits => {
+                let factor = match unit {
+                    's' => 1,
+                    'm' => 60,
+                    'h' => 3600,
+                    'd' => 86400,
+                    'w' => 604800,
+                    _ => return None,
+                };
+                seconds = seconds.checked_add(value.checked_mul(factor)?)?;
+                value = 0;
+                digits = false;
+            }
+            _ => return None,
+        }
+    }
+    (!digits).then_some(seconds)
+}
+
+/// The fully qualified name, with a trailing dot
+fn absolute(name: &str, origin: &str) -> String {
+    match name {
+        "@" => origin.to_owned(),
+        name if name.ends_with('.') => name.to_owned(),
+        name => format!("{name}.{origin}"),
+    }
+}
+
+/// The name relative to the domain, or `@` for the domain itself
+fn relative(absolute: &str, domain: &str) -> Option<String> {
+    let lowercase = absolute.to_ascii_lowercase();
+    let domain = format!("{}.", domain.to_ascii_lowercase());
+    if lowercase == domain {
+        return Some("@".to_owned());
+    }
+    lowercase
+        .strip_suffix(&domain)
+        .and_then(|prefix| prefix.strip_suffix('.'))
+        .map(|prefix| absolute[..prefix.len()].to_owned())
+}
+
+/// Quotes the content of a TXT record, split in strings of at most 255 bytes
+fn quote_txt(content: &str) -> String {
+    let mut strings = vec![];
+    let mut current = String::new();
+    for c in content.chars() {
+        if current.len() + c.len_utf8() > MAX_STRING_LENGTH {
+            strings.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+    }
+    strings.push(current);
+    strings
+        .iter()
+        .map(|string| format!(r#""{}""#, string.replace('\\', r"\\").replace('"', r#"\""#)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Zone {
+    pub fn new<S: Into<String>>(domain_name: S, entries: Vec<DnsEntry>) -> Self {
+        let domain_name = domain_name.into();
+        Self {
+            domain_name: domain_name.trim_end_matches('.').to_owned(),
+            entries,
+        }
+    }
+
+    /// Read the entries of a zone file for this domain.
+    /// The `$ORIGIN` and `$TTL` directives are supported, names outside the domain are rejected.
+    pub fn parse(domain_name: &str, zone_file: &str) -> Result<Self> {
+        let domain_name = domain_name.trim_end_matches('.');
+        let mut origin = format!("{domain_name}.");
+        let mut default_ttl = None;
+        let mut previous_name = None;
+        let mut entries = vec![];
+        for line in lines(zone_file)? {
+            let number = line.number;
+            let mut words = line.words.into_iter().peekable();
+            if !line.indented
+                && let Some(directive) = words.next_if(|word| word.text.starts_with('$'))
+            {
+                let argument = words
+                    .next()
+                    .ok_or_else(|| zone_error(number, "argument missing"))?;
+                match directive.text.to_ascii_uppercase().as_str() {
+                    "$ORIGIN" => origin = absolute(&argument.text, &origin),
+                    "$TTL" => {
+                        default_ttl = Some(
+                            ttl(&argument.text).ok_or_else(|| zone_error(number, "invalid ttl"))?,
+                        )
+                    }
+                    _ => {
+                        return Err(zone_error(
+                            number,
+                            format!("{} is not supported", directive.text),
+                        ));
+                    }
+                }
+                continue;
+            }
+
+            let name = if line.indented {
+                previous_name
+                    .clone()
+                    .ok_or_else(|| zone_error(number, "name missing"))?
+            } else {
+                let name = words
+                    .next()
+                    .ok_or_else(|| zone_error(number, "name missing"))?;
+                relative(&absolute(&name.text, &origin), domain_name).ok_or_else(|| {
+                    zone_error(number, format!("{} is not in {domain_name}", name.text))
+                })?
+            };
+
+            let mut expire = None;
+            while let Some(word) = words.next_if(|word| {
+                word.text.eq_ignore_ascii_case(CLASS)
+                    || (expire.is_none() && ttl(&word.text).is_some())
+            }) {
+                if !word.text.eq_ignore_ascii_case(CLASS) {
+                    expire = ttl(&word.text);
+                }
+            }
+            let expire = expire
+                .or(default_ttl)
+                .ok_or_else(|| zone_error(number, "ttl missing"))?;
+
+            let record_type = words
+                .next()
+                .ok_or_else(|| zone_error(number, "record type missing"))?
+                .text
+                .to_ascii_uppercase()
+                .parse::<RecordType>()
+                .map_err(|error| zone_error(number, error))?;
+            let content = if record_type == RecordType::TXT {
+                words.map(|word| word.text).collect::<String>()
+            } else {
+                words
+                    .map(|word| match word.quoted {
+                        true => format!(r#""{}""#, word.text),
+                        false => word.text,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            let record = DnsRecord::parse(record_type, &content)
+                .map_err(|error| zone_error(number, error))?;
+            entries.push(DnsEntry::new(name.clone(), expire, record));
+            previous_name = Some(name);
+        }
+        Ok(Self::new(domain_name, entries))
+    }
+
+    /// The most common ttl, used for the `$TTL` directive
+    fn default_ttl(&self) -> Option<u32> {
+        let mut counts = HashMap::<u32, usize>::new();
+        for entry in &self.entries {
+            *counts.entry(entry.expire).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .max_by_key(|(expire, count)| (*count, std::cmp::Reverse(*expire)))
+            .map(|(expire, _)| expire)
+    }
+}
+
+/// The zone file, with a ttl on every record
+impl Display for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "$ORIGIN {}.", self.domain_name)?;
+        if let Some(default_ttl) = self.default_ttl() {
+            writeln!(f, "$TTL {default_ttl}")?;
+        }
+        for entry in &self.entries {
+            let content = if entry.entry_type == RecordType::TXT.to_string() {
+                quote_txt(&entry.content)
+            } else {
+                entry.content.clone()
+            };
+            writeln!(
+                f,
+                "{}\t{}\t{CLASS}\t{}\t{}",
+                entry.name, entry.expire, entry.entry_type, content
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Zone, ttl};
+    use crate::Error;
+    use crate::api::dns::{DnsApi, DnsEntry};
+    use crate::client::Client;
+
+    const ZONE_FILE: &str = r#"
+$ORIGIN example.com.
+$TTL 1h
+; managed by another provider
+@   IN  SOA ns1.example.net. hostmaster.example.com. (
+        2024010101 ; serial
+        14400      ; refresh
+        3600       ; retry
+        604800     ; expire
+        300 )      ; minimum
+    IN  NS  ns1.example.net.
+    IN  MX  10 mail
+www 300 IN  A   192.0.2.1
+        IN  AAAA 2001:db8::1
+mail.example.com. 5m A 192.0.2.2
+selector._domainkey IN TXT "v=DKIM1; k=rsa; " "p=MIIBIjAN"
+quote   TXT "say \"hello\" \059 \\"
+@   CAA 0 issue "letsencrypt.org"
+"#;
+
+    fn zone_error(zone_file: &str) -> String {
+        match Zone::parse("example.com", zone_file) {
+            Err(Error::ParseZone(message)) => message,
+            Err(error) => panic!("Unexpected error {error}"),
+            Ok(zone) => panic!("Zone {zone:?} accepted"),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let zone = Zone::parse("example.com.", ZONE_FILE).unwrap();
+        assert_eq!(zone.domain_name, "example.com");
+        let lines = zone
+            .entries
+            .iter()
+            .map(DnsEntry::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "@ 3600 SOA ns1.example.net. hostmaster.example.com. 2024010101 14400 3600 604800 300",
+                "@ 3600 NS ns1.example.net.",
+                "@ 3600 MX 10 mail",
+                "www 300 A 192.0.2.1",
+                "www 3600 AAAA 2001:db8::1",
+                "mail 300 A 192.0.2.2",
+                "selector._domainkey 3600 TXT v=DKIM1; k=rsa; p=MIIBIjAN",
+                r#"quote 3600 TXT say "hello" ; \"#,
+                r#"@ 3600 CAA 0 issue "letsencrypt.org""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn export_and_import() {
+        let entries = Client::fixture("dns")
+            .dns_entry_list("transipdemo.be")
+            .unwrap();
+        let zone = Zone::new("transipdemo.be", entries);
+        let zone_file = zone.to_string();
+        assert!(zone_file.starts_with("$ORIGIN transipdemo.be.\n$TTL 86400\n"));
+        assert!(zone_file.contains(r#"_dmarc	86400	IN	TXT	"v=DMARC1; p=none;""#));
+        assert_eq!(Zone::parse("transipdemo.be", &zone_file).unwrap(), zone);
+    }
+
+    #[test]
+    fn long_txt() {
+        let content = format!("v=DKIM1; p={}\"", "A".repeat(400));
+        let entry = DnsEntry {
+            name: "key._domainkey".to_owned(),
+            expire: 300,
+            entry_type: "TXT".to_owned(),
+            content,
+        };
+        let zone = Zone::new("example.com", vec![entry]);
+        let zone_file = zone.to_string();
+        assert!(zone_file.contains(r#"AAAA" "AAAA"#));
+        assert!(zone_file.contains(r#"\"""#));
+        assert_eq!(Zone::parse("example.com", &zone_file).unwrap(), zone);
+    }
+
+    #[test]
+    fn escaped_bytes() {
+        let zone = Zone::parse("example.com", r#"www 300 TXT "caf\195\169 \+12""#).unwrap();
+        assert_eq!(zone.entries[0].content, "café +12");
+        assert_eq!(
+            zone_error(r#"www 300 TXT "caf\233""#),
+            "line 1: invalid utf-8 in quoted text"
+        );
+    }
+
+    #[test]
+    fn ttl_units() {
+        assert_eq!(ttl("300"), Some(300));
+        assert_eq!(ttl("1h30m"), Some(5400));
+        assert_eq!(ttl("1W"), Some(604800));
+        assert_eq!(ttl("1x"), None);
+        assert_eq!(ttl("h"), None);
+        assert_eq!(ttl("1h30"), None);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            zone_error("www.example.org. 300 A 192.0.2.1"),
+            "line 1: www.example.org. is not in example.com"
+        );
+        assert_eq!(zone_error("\nwww A 192.0.2.1"), "line 2: ttl missing");
+        assert_eq!(
+            zone_error("$INCLUDE other.zone"),
+            "line 1: $INCLUDE is not supported"
+        );
+        assert_eq!(
+            zone_error("www 300 A 192.0.2.1 (\n"),
+            "line 2: closing parenthesis missing"
+        );
+        assert_eq!(
+            zone_error("www 300 TXT \"open\n"),
+            "line 1: closing quote missing"
+        );
+        assert_eq!(
+            zone_error("$TTL 300\nwww AAAA 192.0.2.1"),
+            "line 2: Parse Dns entry: invalid IPv6 address"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::api::{Link, Page, Pages};
 use crate::{
     HasName, Result,
     client::{Client, Url},
@@ -11,7 +12,7 @@ const DOMAINS_INCLUDES: &str = "?include=nameservers,contacts";
 // const NAMESERVERS: &str = "nameservers";
 
 trait UrlDomain {
-    fn domain(&self, domain_name: &str) -> String;
+    fn domain(&self, domain_name: &str, includes: bool) -> String;
     // fn domain_dns(&self, domain_name: &str) -> String;
     // fn domain_nameservers(&self, domain_name: &str) -> String;
     fn domains(&self, includes: bool) -> String;
@@ -23,7 +24,8 @@ pub trait DomainApi {
     ///
     /// Example
     ///
-    /// ```
+    /// ```no_run
+    /// # #[cfg(feature = "ureq")] {
     /// use transip::{api::domain::DomainApi, HasNames};
     ///
     /// assert_eq!(
@@ -36,14 +38,27 @@ pub trait DomainApi {
     ///         "transipdemonstratie.nl",
     ///     ]
     /// );
+    /// # }
     /// ```
     ///
-    fn domain_list(&mut self) -> Result<Vec<Domain>>;
+    fn domain_list(&self) -> Result<Vec<Domain>>;
+    /// Lazy version of [`domain_list`](DomainApi::domain_list), that requests one page at a time
+    fn domain_iter(&self) -> Pages<'_, Domain>;
 
-    fn domain_item(&mut self, name: &str) -> Result<Domain>;
+    /// The domain includes its nameservers and contacts, like the ones from [`domain_list`](DomainApi::domain_list)
+    fn domain_item(&self, name: &str) -> Result<Domain>;
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/// Async version of [`DomainApi`]
+#[cfg(feature = "async")]
+pub trait AsyncDomainApi {
+    /// See <https://api.transip.nl/rest/docs.html#domains-domains-get>
+    fn domain_list(&self) -> impl Future<Output = Result<Vec<Domain>>> + Send;
+
+    fn domain_item(&self, name: &str) -> impl Future<Output = Result<Domain>> + Send;
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct WhoisContact {
     #[serde(rename = "type")]
@@ -68,7 +83,7 @@ pub struct NameServerList {
     pub nameservers: Vec<NameServer>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct NameServer {
     pub hostname: String,
     pub ipv4: Option<String>,
@@ -81,7 +96,7 @@ impl Display for NameServer {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Clone, Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Domain {
     pub name: String,
@@ -122,6 +137,20 @@ pub struct DomainItem {
 #[derive(Deserialize, Serialize)]
 pub struct DomainList {
     domains: Vec<Domain>,
+    #[serde(rename = "_links", default)]
+    links: Vec<Link>,
+}
+
+impl Page for DomainList {
+    type Item = Domain;
+
+    fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    fn into_items(self) -> Vec<Domain> {
+        self.domains
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize, Debug)]
@@ -158,8 +187,13 @@ impl From<DnsEntry> for DnsEntryItem {
 }
 
 impl UrlDomain for Url {
-    fn domain(&self, domain_name: &str) -> String {
-        format!("{}/{}", self.domains(false), domain_name)
+    fn domain(&self, domain_name: &str, includes: bool) -> String {
+        format!(
+            "{}/{}{}",
+            self.domains(false),
+            domain_name,
+            if includes { DOMAINS_INCLUDES } else { "" }
+        )
     }
 
     // fn domain_dns(&self, domain_name: &str) -> String {
@@ -181,13 +215,30 @@ impl UrlDomain for Url {
 }
 
 impl DomainApi for Client {
-    fn domain_list(&mut self) -> Result<Vec<Domain>> {
-        self.get::<DomainList>(&self.url.domains(true))
-            .map(|list| list.domains)
+    fn domain_list(&self) -> Result<Vec<Domain>> {
+        self.domain_iter().collect()
+    }
+
+    fn domain_iter(&self) -> Pages<'_, Domain> {
+        let url = self.url.domains(true);
+        Pages::new::<DomainList>(self, url)
+    }
+
+    fn domain_item(&self, name: &str) -> Result<Domain> {
+        self.get::<DomainItem>(&self.url.domain(name, true))
+            .map(|item| item.domain)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncDomainApi for crate::AsyncClient {
+    async fn domain_list(&self) -> Result<Vec<Domain>> {
+        self.get_all::<DomainList>(&self.url.domains(true)).await
     }
 
-    fn domain_item(&mut self, name: &str) -> Result<Domain> {
-        self.get::<DomainItem>(&self.url.domain(name))
+    async fn domain_item(&self, name: &str) -> Result<Domain> {
+        self.get::<DomainItem>(&self.url.domain(name, true))
+            .await
             .map(|item| item.domain)
     }
 }
@@ -199,7 +250,7 @@ mod test {
 
     #[test]
     fn domains() {
-        let domains = Client::demo().domain_list().unwrap();
+        let domains = Client::fixture("domain").domain_list().unwrap();
         let names = domains.names();
         assert_eq!(
             names,
@@ -215,7 +266,9 @@ mod test {
 
     #[test]
     fn domain_item() {
-        let domain = Client::demo().domain_item("transipdemo.be").unwrap();
+        let domain = Client::fixture("domain")
+            .domain_item("transipdemo.be")
+            .unwrap();
         dbg!(domain);
     }
 }
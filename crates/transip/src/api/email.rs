@@ -1,5 +1,6 @@
 use std::str::FromStr;
 
+use crate::api::{Link, Page, Pages};
 use crate::{Client, Error, Result, client::Url};
 use serde::{Deserialize, Serialize};
 // use ureq::serde_json::Value;
@@ -19,21 +20,61 @@ trait UrlEmail {
 }
 
 pub trait EmailApi {
-    fn mailbox_delete(&mut self, domain_name: &str, id: &str) -> Result<()>;
-    fn mailbox_insert(&mut self, domain_name: &str, mailbox: MailboxInsert) -> Result<()>;
-    fn mailbox_item(&mut self, domain_name: &str, id: &str) -> Result<Mailbox>;
-    fn mailbox_list(&mut self, domain_name: &str) -> Result<Vec<Mailbox>>;
-    fn mailforward_delete(&mut self, domain_name: &str, id: &str) -> Result<()>;
+    fn mailbox_delete(&self, domain_name: &str, id: &str) -> Result<()>;
+    fn mailbox_insert(&self, domain_name: &str, mailbox: MailboxInsert) -> Result<()>;
+    fn mailbox_item(&self, domain_name: &str, id: &str) -> Result<Mailbox>;
+    fn mailbox_list(&self, domain_name: &str) -> Result<Vec<Mailbox>>;
+    /// Lazy version of [`mailbox_list`](EmailApi::mailbox_list), that requests one page at a time
+    fn mailbox_iter(&self, domain_name: &str) -> Pages<'_, Mailbox>;
+    fn mailforward_delete(&self, domain_name: &str, id: &str) -> Result<()>;
+    fn mailforward_insert(&self, domain_name: &str, mail_forward: MailForwardInsert) -> Result<()>;
+    fn mailforward_item(&self, domain_name: &str, id: &str) -> Result<MailForward>;
+    fn mailforward_list(&self, domain_name: &str) -> Result<Vec<MailForward>>;
+    /// Lazy version of [`mailforward_list`](EmailApi::mailforward_list), that requests one page at a time
+    fn mailforward_iter(&self, domain_name: &str) -> Pages<'_, MailForward>;
+}
+
+/// Async version of [`EmailApi`]
+#[cfg(feature = "async")]
+pub trait AsyncEmailApi {
+    fn mailbox_delete(
+        &self,
+        domain_name: &str,
+        id: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn mailbox_insert(
+        &self,
+        domain_name: &str,
+        mailbox: MailboxInsert,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn mailbox_item(
+        &self,
+        domain_name: &str,
+        id: &str,
+    ) -> impl Future<Output = Result<Mailbox>> + Send;
+    fn mailbox_list(&self, domain_name: &str) -> impl Future<Output = Result<Vec<Mailbox>>> + Send;
+    fn mailforward_delete(
+        &self,
+        domain_name: &str,
+        id: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
     fn mailforward_insert(
-        &mut self,
+        &self,
         domain_name: &str,
         mail_forward: MailForwardInsert,
-    ) -> Result<()>;
-    fn mailforward_item(&mut self, domain_name: &str, id: &str) -> Result<MailForward>;
-    fn mailforward_list(&mut self, domain_name: &str) -> Result<Vec<MailForward>>;
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn mailforward_item(
+        &self,
+        domain_name: &str,
+        id: &str,
+    ) -> impl Future<Output = Result<MailForward>> + Send;
+    fn mailforward_list(
+        &self,
+        domain_name: &str,
+    ) -> impl Future<Output = Result<Vec<MailForward>>> + Send;
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct MailForward {
     pub id: u64,
@@ -53,6 +94,20 @@ pub struct MailForwardItem {
 #[serde(rename_all = "camelCase")]
 pub struct MailForwardList {
     pub forwards: Vec<MailForward>,
+    #[serde(rename = "_links", default)]
+    pub links: Vec<Link>,
+}
+
+impl Page for MailForwardList {
+    type Item = MailForward;
+
+    fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    fn into_items(self) -> Vec<MailForward> {
+        self.forwards
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
@@ -100,7 +155,7 @@ pub struct MailListList {
     mail_lists: Vec<MailList>,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Mailbox {
     pub available_disk_space: u64,
@@ -152,6 +207,20 @@ pub struct MailboxItem {
 #[serde(rename_all = "camelCase")]
 pub struct MailboxList {
     pub mailboxes: Vec<Mailbox>,
+    #[serde(rename = "_links", default)]
+    pub links: Vec<Link>,
+}
+
+impl Page for MailboxList {
+    type Item = Mailbox;
+
+    fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    fn into_items(self) -> Vec<Mailbox> {
+        self.mailboxes
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -236,48 +305,102 @@ impl UrlEmail for Url {
 }
 
 impl EmailApi for Client {
-    fn mailbox_delete(&mut self, domain_name: &str, id: &str) -> Result<()> {
+    fn mailbox_delete(&self, domain_name: &str, id: &str) -> Result<()> {
         self.delete_no_object(&self.url.mailbox_domain_item(domain_name, id))
     }
 
-    fn mailbox_insert(&mut self, domain_name: &str, mailbox: MailboxInsert) -> Result<()> {
+    fn mailbox_insert(&self, domain_name: &str, mailbox: MailboxInsert) -> Result<()> {
         self.post(&self.url.mailbox_domain(domain_name), mailbox)
     }
 
-    fn mailbox_item(&mut self, domain_name: &str, id: &str) -> Result<Mailbox> {
+    fn mailbox_item(&self, domain_name: &str, id: &str) -> Result<Mailbox> {
         self.get::<MailboxItem>(&self.url.mailbox_domain_item(domain_name, id))
             .map(|item| item.mailbox)
     }
 
-    fn mailbox_list(&mut self, domain_name: &str) -> Result<Vec<Mailbox>> {
-        self.get::<MailboxList>(&self.url.mailbox_domain(domain_name))
-            .map(|list| list.mailboxes)
+    fn mailbox_list(&self, domain_name: &str) -> Result<Vec<Mailbox>> {
+        self.mailbox_iter(domain_name).collect()
+    }
+
+    fn mailbox_iter(&self, domain_name: &str) -> Pages<'_, Mailbox> {
+        let url = self.url.mailbox_domain(domain_name);
+        Pages::new::<MailboxList>(self, url)
     }
 
-    fn mailforward_delete(&mut self, domain_name: &str, id: &str) -> Result<()> {
+    fn mailforward_delete(&self, domain_name: &str, id: &str) -> Result<()> {
         self.delete_no_object(&self.url.mailforward_domain_item(domain_name, id))
     }
 
-    fn mailforward_insert(
-        &mut self,
+    fn mailforward_insert(&self, domain_name: &str, mail_forward: MailForwardInsert) -> Result<()> {
+        self.post(&self.url.mailforward_domain(domain_name), mail_forward)
+    }
+
+    fn mailforward_item(&self, domain_name: &str, id: &str) -> Result<MailForward> {
+        self.get::<MailForwardItem>(&self.url.mailforward_domain_item(domain_name, id))
+            .map(|item| item.forward)
+    }
+
+    fn mailforward_list(&self, domain_name: &str) -> Result<Vec<MailForward>> {
+        self.mailforward_iter(domain_name).collect()
+    }
+
+    fn mailforward_iter(&self, domain_name: &str) -> Pages<'_, MailForward> {
+        let url = self.url.mailforward_domain(domain_name);
+        Pages::new::<MailForwardList>(self, url)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncEmailApi for crate::AsyncClient {
+    async fn mailbox_delete(&self, domain_name: &str, id: &str) -> Result<()> {
+        self.delete_no_object(&self.url.mailbox_domain_item(domain_name, id))
+            .await
+    }
+
+    async fn mailbox_insert(&self, domain_name: &str, mailbox: MailboxInsert) -> Result<()> {
+        self.post(&self.url.mailbox_domain(domain_name), mailbox)
+            .await
+    }
+
+    async fn mailbox_item(&self, domain_name: &str, id: &str) -> Result<Mailbox> {
+        self.get::<MailboxItem>(&self.url.mailbox_domain_item(domain_name, id))
+            .await
+            .map(|item| item.mailbox)
+    }
+
+    async fn mailbox_list(&self, domain_name: &str) -> Result<Vec<Mailbox>> {
+        self.get_all::<MailboxList>(&self.url.mailbox_domain(domain_name))
+            .await
+    }
+
+    async fn mailforward_delete(&self, domain_name: &str, id: &str) -> Result<()> {
+        self.delete_no_object(&self.url.mailforward_domain_item(domain_name, id))
+            .await
+    }
+
+    async fn mailforward_insert(
+        &self,
         domain_name: &str,
         mail_forward: MailForwardInsert,
     ) -> Result<()> {
         self.post(&self.url.mailforward_domain(domain_name), mail_forward)
+            .await
     }
 
-    fn mailforward_item(&mut self, domain_name: &str, id: &str) -> Result<MailForward> {
+    async fn mailforward_item(&self, domain_name: &str, id: &str) -> Result<MailForward> {
         self.get::<MailForwardItem>(&self.url.mailforward_domain_item(domain_name, id))
+            .await
             .map(|item| item.forward)
     }
 
-    fn mailforward_list(&mut self, domain_name: &str) -> Result<Vec<MailForward>> {
-        self.get::<MailForwardList>(&self.url.mailforward_domain(domain_name))
-            .map(|list| list.forwards)
+    async fn mailforward_list(&self, domain_name: &str) -> Result<Vec<MailForward>> {
+        self.get_all::<MailForwardList>(&self.url.mailforward_domain(domain_name))
+            .await
     }
 }
 
 #[cfg(not(target_family = "wasm"))]
+#[cfg(feature = "ureq")]
 #[cfg(test)]
 mod test {
     const DEFAULT_CONTENT_TYPE: &str = "application/json";
@@ -327,7 +450,7 @@ mod test {
                 .header("Content-Type", DEFAULT_CONTENT_TYPE);
         });
 
-        let mut client = Client::test(server.base_url());
+        let client = Client::test(server.base_url());
         let mailbox_list = client.mailbox_list("paulmin.nl").unwrap();
 
         assert_eq!(mailbox_list, vec![mailbox_for_paulmin_demo()]);
@@ -346,7 +469,7 @@ mod test {
                 .body(body)
                 .header("Content-Type", DEFAULT_CONTENT_TYPE);
         });
-        let mut client = Client::test(server.base_url());
+        let client = Client::test(server.base_url());
 
         let item = client
             .mailbox_item("paulmin.nl", "info@paulmin.nl")
@@ -366,7 +489,7 @@ mod test {
             then.status(200);
         });
 
-        let mut client = Client::test(server.base_url());
+        let client = Client::test(server.base_url());
         client
             .mailbox_delete("paulmin.nl", "info@paulmin.nl")
             .unwrap();
@@ -385,7 +508,7 @@ mod test {
     //             then.status(201);
     //         });
     //
-    //         let mut client = Client::test(server.base_url());
+    //         let client = Client::test(server.base_url());
     //         let body_object = super::MailboxInsert {
     //             local_part: "test".to_owned(),
     //             max_disk_usage: 0,
@@ -408,7 +531,7 @@ mod test {
                 .header("Content-Type", DEFAULT_CONTENT_TYPE);
         });
 
-        let mut client = Client::test(server.base_url());
+        let client = Client::test(server.base_url());
         let mailforward_list = client.mailforward_list(DOMAIN_NAME).unwrap();
 
         assert_eq!(mailforward_list, vec![mail_forward_for_transip_demo()]);
@@ -439,7 +562,7 @@ mod test {
         //     .named(name)
         //     .mount(&server);
 
-        let mut client = Client::test(server.url(""));
+        let client = Client::test(server.url(""));
 
         let item = client.mailforward_item(DOMAIN_NAME, "292883").unwrap();
 
@@ -464,7 +587,7 @@ mod test {
         //     .named(name)
         //     .mount(&server);
 
-        let mut client = Client::test(server.url(""));
+        let client = Client::test(server.url(""));
         client.mailforward_delete(DOMAIN_NAME, "292883").unwrap();
         mock.assert_calls(1);
     }
@@ -492,7 +615,7 @@ mod test {
     //         //     .named(name)
     //         //     .mount(&server);
     //
-    //         let mut client = Client::test(server.url(""));
+    //         let client = Client::test(server.url(""));
     //         let entry = MailForwardInsert {
     //             local_part: "test".to_owned(),
     //             forward_to: "info@paulmin.nl".to_owned(),
@@ -24,7 +24,7 @@ pub trait GeneralApi {
     /// The positive result of this method should always be `pong`.
     /// You can use the demo token.
     ///
-    fn api_test(&mut self) -> Result<String>;
+    fn api_test(&self) -> Result<String>;
 
     /// See <https://api.transip.nl/rest/docs.html#general-availabilityzone-get>
     ///
@@ -33,18 +33,39 @@ pub trait GeneralApi {
     ///
     /// # Example
     ///
-    /// ```
+    /// ```no_run
+    /// # #[cfg(feature = "ureq")] {
     /// use transip::{api::general::GeneralApi, Client};
     /// let zones = Client::demo().availability_zones().unwrap();
+    /// # }
     /// ```
     ///
-    fn availability_zones(&mut self) -> Result<Vec<AvailabilityZone>>;
+    fn availability_zones(&self) -> Result<Vec<AvailabilityZone>>;
 
     /// See <https://api.transip.nl/rest/docs.html#general-products-get>
-    fn products(&mut self) -> Result<Products>;
+    fn products(&self) -> Result<Products>;
 
     /// See <https://api.transip.nl/rest/docs.html#general-elements-get>
-    fn product_elements(&mut self, name: &str) -> Result<Vec<ProductElement>>;
+    fn product_elements(&self, name: &str) -> Result<Vec<ProductElement>>;
+}
+
+/// Async version of [`GeneralApi`]
+#[cfg(feature = "async")]
+pub trait AsyncGeneralApi {
+    /// See <https://api.transip.nl/rest/docs.html#general-apitest-get>
+    fn api_test(&self) -> impl Future<Output = Result<String>> + Send;
+
+    /// See <https://api.transip.nl/rest/docs.html#general-availabilityzone-get>
+    fn availability_zones(&self) -> impl Future<Output = Result<Vec<AvailabilityZone>>> + Send;
+
+    /// See <https://api.transip.nl/rest/docs.html#general-products-get>
+    fn products(&self) -> impl Future<Output = Result<Products>> + Send;
+
+    /// See <https://api.transip.nl/rest/docs.html#general-elements-get>
+    fn product_elements(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = Result<Vec<ProductElement>>> + Send;
 }
 
 #[derive(Deserialize, Serialize)]
@@ -161,22 +182,47 @@ impl UrlGeneral for Url {
 }
 
 impl GeneralApi for Client {
-    fn api_test(&mut self) -> Result<String> {
+    fn api_test(&self) -> Result<String> {
         self.get::<Ping>(&self.url.api_test()).map(|p| p.ping)
     }
 
-    fn availability_zones(&mut self) -> Result<Vec<AvailabilityZone>> {
+    fn availability_zones(&self) -> Result<Vec<AvailabilityZone>> {
+        self.get::<AvailabilityZones>(&self.url.availability_zones())
+            .map(|list| list.availability_zones)
+    }
+
+    fn product_elements(&self, name: &str) -> Result<Vec<ProductElement>> {
+        self.get::<ProductElements>(&self.url.product_elements(name))
+            .map(|list| list.product_elements)
+    }
+
+    fn products(&self) -> Result<Products> {
+        self.get::<ProductList>(&self.url.products())
+            .map(|list| list.products)
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncGeneralApi for crate::AsyncClient {
+    async fn api_test(&self) -> Result<String> {
+        self.get::<Ping>(&self.url.api_test()).await.map(|p| p.ping)
+    }
+
+    async fn availability_zones(&self) -> Result<Vec<AvailabilityZone>> {
         self.get::<AvailabilityZones>(&self.url.availability_zones())
+            .await
             .map(|list| list.availability_zones)
     }
 
-    fn product_elements(&mut self, name: &str) -> Result<Vec<ProductElement>> {
+    async fn product_elements(&self, name: &str) -> Result<Vec<ProductElement>> {
         self.get::<ProductElements>(&self.url.product_elements(name))
+            .await
             .map(|list| list.product_elements)
     }
 
-    fn products(&mut self) -> Result<Products> {
+    async fn products(&self) -> Result<Products> {
         self.get::<ProductList>(&self.url.products())
+            .await
             .map(|list| list.products)
     }
 }
@@ -189,20 +235,20 @@ mod tests {
 
     #[test]
     fn api_test() {
-        let ping = Client::demo().api_test().unwrap();
+        let ping = Client::fixture("general").api_test().unwrap();
         assert_eq!(ping, "pong".to_owned());
     }
 
     #[test]
     fn availability_zones() {
-        let zones = Client::demo().availability_zones().unwrap();
+        let zones = Client::fixture("general").availability_zones().unwrap();
         let names = zones.names();
         assert_eq!(names, vec!["ams0", "rtm0",],);
     }
 
     #[test]
     fn vps_products() {
-        let products = Client::demo().products().unwrap().vps;
+        let products = Client::fixture("general").products().unwrap().vps;
         let names = products.names();
         assert_eq!(
             names,
@@ -234,7 +280,7 @@ mod tests {
 
     #[test]
     fn haip_products() {
-        let products: Vec<Product> = Client::demo().products().unwrap().haip;
+        let products: Vec<Product> = Client::fixture("general").products().unwrap().haip;
         let names = products.names();
 
         assert_eq!(names, vec!["haip-basic-contract", "haip-pro-contract",]);
@@ -242,7 +288,7 @@ mod tests {
 
     #[test]
     fn haip_basic_product_elements() {
-        let elements = Client::demo()
+        let elements = Client::fixture("general")
             .product_elements("haip-basic-contract")
             .unwrap();
         let names = elements.names();
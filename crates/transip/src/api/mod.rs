@@ -4,3 +4,8 @@ pub mod domain;
 pub mod email;
 pub mod general;
 pub mod vps;
+
+mod pagination;
+
+pub(crate) use pagination::{DEFAULT_PAGE_SIZE, Page};
+pub use pagination::{Link, Pages};
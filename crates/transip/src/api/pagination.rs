@@ -0,0 +1,357 @@
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tracing::warn;
+
+use crate::{Client, Result};
+
+/// Page size used when the client is not configured otherwise
+pub(crate) const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// Upper limit on the number of pages requested for a single list
+pub(crate) const MAX_PAGES: u32 = 1000;
+
+const PAGINATION_RELS: [&str; 4] = ["first", "previous", "next", "last"];
+
+/// Link returned by the api in the `_links` field of a list response
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Link {
+    pub rel: String,
+    pub link: String,
+}
+
+/// Response of a list endpoint that supports the `page` and `pageSize` query parameters
+pub(crate) trait Page: DeserializeOwned {
+    type Item: Serialize;
+    fn links(&self) -> &[Link];
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+/// Url of a single page of a list endpoint
+pub(crate) fn page_url(url: &str, page: u32, page_size: u32) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}page={page}&pageSize={page_size}")
+}
+
+/// Decides if another page should be requested.
+/// If the api returned pagination links, a `next` link is needed.
+/// Otherwise a full page means there could be more.
+pub(crate) fn has_next_page(links: &[Link], count: usize, page_size: u32) -> bool {
+    if links
+        .iter()
+        .any(|link| PAGINATION_RELS.contains(&link.rel.as_str()))
+    {
+        links.iter().any(|link| link.rel == "next")
+    } else {
+        count > 0 && count == page_size as usize
+    }
+}
+
+/// Keeps track of the pages of a list.
+/// Paging stops when there is no next page, when a page repeats the previous page,
+/// for an endpoint that ignores `page` and `pageSize`, or after [`MAX_PAGES`] pages.
+pub(crate) struct Pagination {
+    page: u32,
+    page_size: u32,
+    previous: Option<Vec<u8>>,
+    done: bool,
+}
+
+impl Pagination {
+    pub(crate) fn new(page_size: u32) -> Self {
+        Self {
+            page: 1,
+            page_size,
+            previous: None,
+            done: false,
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Url of the next page
+    pub(crate) fn url(&self, url: &str) -> String {
+        page_url(url, self.page, self.page_size)
+    }
+
+    /// Returns the items of the page, none if the page repeats the previous page
+    pub(crate) fn receive<T: Serialize>(&mut self, items: Vec<T>, links: &[Link]) -> Vec<T> {
+        let current = serde_json::to_vec(&items).ok();
+        if current.is_some() && current == self.previous {
+            warn!("Page {} repeats the previous page", self.page);
+            self.done = true;
+            return vec![];
+        }
+        self.done = !has_next_page(links, items.len(), self.page_size);
+        if !self.done && self.page >= MAX_PAGES {
+            warn!("Stopped after {} pages", MAX_PAGES);
+            self.done = true;
+        }
+        self.previous = current;
+        self.page += 1;
+        items
+    }
+
+    /// Stop after an error
+    pub(crate) fn fail(&mut self) {
+        self.done = true;
+    }
+}
+
+type Fetch<T> = fn(&Client, &str) -> Result<(Vec<T>, Vec<Link>)>;
+
+fn fetch<P: Page>(client: &Client, url: &str) -> Result<(Vec<P::Item>, Vec<Link>)> {
+    client.get::<P>(url).map(|page| {
+        let links = page.links().to_vec();
+        (page.into_items(), links)
+    })
+}
+
+/// Lazy iterator over all items of a list endpoint.
+///
+/// A page is only requested when the items of the previous page are consumed,
+/// so large accounts can be processed without loading everything at once.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "ureq")] {
+/// use transip::{api::domain::DomainApi, Client};
+///
+/// let client = Client::demo();
+/// for domain in client.domain_iter() {
+///     println!("{}", domain.unwrap().name);
+/// }
+/// # }
+/// ```
+pub struct Pages<'a, T> {
+    client: &'a Client,
+    url: String,
+    fetch: Fetch<T>,
+    pagination: Pagination,
+    items: std::vec::IntoIter<T>,
+}
+
+impl<'a, T> Pages<'a, T> {
+    pub(crate) fn new<P>(client: &'a Client, url: String) -> Self
+    where
+        P: Page<Item = T>,
+    {
+        Self {
+            client,
+            url,
+            fetch: fetch::<P>,
+            pagination: Pagination::new(client.page_size()),
+            items: vec![].into_iter(),
+        }
+    }
+}
+
+impl<T: Serialize> Iterator for Pages<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(Ok(item));
+            }
+            if self.pagination.is_done() {
+                return None;
+            }
+            match (self.fetch)(self.client, &self.pagination.url(&self.url)) {
+                Ok((items, links)) => {
+                    self.items = self.pagination.receive(items, &links).into_iter();
+                }
+                Err(error) => {
+                    self.pagination.fail();
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl crate::AsyncClient {
+    /// Requests all pages of a list endpoint
+    pub(crate) async fn get_all<P>(&self, url: &str) -> Result<Vec<P::Item>>
+    where
+        P: Page,
+    {
+        let mut result = vec![];
+        let mut pagination = Pagination::new(self.page_size());
+        while !pagination.is_done() {
+            let response = self.get::<P>(&pagination.url(url)).await?;
+            let links = response.links().to_vec();
+            result.extend(pagination.receive(response.into_items(), &links));
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "ureq")]
+    use httpmock::Method::GET;
+
+    use super::{Link, MAX_PAGES, Pagination, has_next_page, page_url};
+    use crate::api::vps::VpsApi;
+    use crate::transport::{MemoryTransport, Method, Response};
+    use crate::{Client, HasNames};
+
+    fn link(rel: &str) -> Link {
+        Link {
+            rel: rel.to_owned(),
+            link: format!("https://api.transip.nl/v6/vps?{rel}"),
+        }
+    }
+
+    fn vps(name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "uuid": "",
+            "description": "",
+            "productName": "vps-bladevps-x1",
+            "operatingSystem": "debian",
+            "diskSize": 1,
+            "memorySize": 1,
+            "cpus": 1,
+            "status": "running",
+            "ipAddress": "192.0.2.1",
+            "macAddress": "",
+            "currentSnapshots": 0,
+            "maxSnapshots": 1,
+            "isLocked": false,
+            "isBlocked": false,
+            "isCustomerLocked": false,
+            "availabilityZone": "ams0",
+            "tags": []
+        })
+    }
+
+    #[test]
+    fn url() {
+        assert_eq!(
+            page_url("https://api.transip.nl/v6/vps", 2, 25),
+            "https://api.transip.nl/v6/vps?page=2&pageSize=25"
+        );
+        assert_eq!(
+            page_url("https://api.transip.nl/v6/domains?include=contacts", 1, 25),
+            "https://api.transip.nl/v6/domains?include=contacts&page=1&pageSize=25"
+        );
+    }
+
+    #[test]
+    fn next_page() {
+        assert!(has_next_page(&[link("first"), link("next")], 2, 10));
+        assert!(!has_next_page(&[link("first"), link("last")], 10, 10));
+        assert!(has_next_page(&[link("self")], 10, 10));
+        assert!(!has_next_page(&[], 9, 10));
+        assert!(!has_next_page(&[], 0, 0));
+    }
+
+    #[test]
+    fn repeated_page() {
+        let mut pagination = Pagination::new(2);
+        assert_eq!(pagination.receive(vec![1, 2], &[]), vec![1, 2]);
+        assert!(!pagination.is_done());
+        assert!(pagination.url("vps").ends_with("page=2&pageSize=2"));
+        assert!(pagination.receive(vec![1, 2], &[]).is_empty());
+        assert!(pagination.is_done());
+    }
+
+    #[test]
+    fn page_limit() {
+        let mut pagination = Pagination::new(1);
+        let next = [link("next")];
+        for page in 1..=MAX_PAGES {
+            assert!(!pagination.is_done());
+            pagination.receive(vec![page], &next);
+        }
+        assert!(pagination.is_done());
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn follow_pages() {
+        let server = httpmock::MockServer::start();
+        let first = server.mock(|when, then| {
+            when.method(GET)
+                .path("/vps")
+                .query_param("page", "1")
+                .query_param("pageSize", "2");
+            then.status(200).json_body(serde_json::json!({
+                "vpss": [vps("vps1"), vps("vps2")],
+                "_links": [
+                    {"rel": "first", "link": "/vps?page=1"},
+                    {"rel": "next", "link": "/vps?page=2"}
+                ]
+            }));
+        });
+        let second = server.mock(|when, then| {
+            when.method(GET)
+                .path("/vps")
+                .query_param("page", "2")
+                .query_param("pageSize", "2");
+            then.status(200).json_body(serde_json::json!({
+                "vpss": [vps("vps3")],
+                "_links": [
+                    {"rel": "first", "link": "/vps?page=1"},
+                    {"rel": "previous", "link": "/vps?page=1"}
+                ]
+            }));
+        });
+
+        let client = Client::test(server.base_url()).with_page_size(2);
+        let mut pages = client.vps_iter();
+        assert_eq!(pages.next().unwrap().unwrap().name, "vps1");
+        first.assert_calls(1);
+        second.assert_calls(0);
+        drop(pages);
+
+        let list = client.vps_list().unwrap();
+        assert_eq!(list.names(), vec!["vps1", "vps2", "vps3"]);
+        second.assert_calls(1);
+    }
+
+    #[test]
+    fn pages_without_links() {
+        let page = |names: &[&str]| {
+            let vpss = names.iter().map(|name| vps(name)).collect::<Vec<_>>();
+            Response::json(200, serde_json::json!({ "vpss": vpss }).to_string())
+        };
+        let transport = MemoryTransport::default()
+            .respond(
+                Method::Get,
+                "/vps?page=1&pageSize=2",
+                page(&["vps1", "vps2"]),
+            )
+            .respond(
+                Method::Get,
+                "/vps?page=2&pageSize=2",
+                page(&["vps3", "vps4"]),
+            )
+            .respond(Method::Get, "/vps?page=3&pageSize=2", page(&["vps5"]));
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone())
+            .with_page_size(2);
+        assert_eq!(
+            client.vps_list().unwrap().names(),
+            vec!["vps1", "vps2", "vps3", "vps4", "vps5"]
+        );
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    #[test]
+    fn page_parameters_ignored() {
+        let body = serde_json::json!({"vpss": [vps("vps1"), vps("vps2")]});
+        let transport = MemoryTransport::default().respond(
+            Method::Get,
+            "/vps",
+            Response::json(200, body.to_string()),
+        );
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone())
+            .with_page_size(2);
+        assert_eq!(client.vps_list().unwrap().names(), vec!["vps1", "vps2"]);
+        assert_eq!(transport.requests().len(), 2);
+    }
+}
@@ -1,3 +1,5 @@
+pub use crate::api::Link;
+use crate::api::{Page, Pages};
 use crate::{
     HasName, Result,
     client::{Client, Url},
@@ -19,36 +21,66 @@ trait UrlVps {
 )]
 pub trait TransipApiVps {
     /// [VPS list](https://api.transip.nl/rest/docs.html#vps-vps-get)
-    fn vps_list(&mut self) -> Result<Vec<Vps>>;
+    fn vps_list(&self) -> Result<Vec<Vps>>;
 
-    fn vps(&mut self, name: &str) -> Result<Vps>;
+    fn vps(&self, name: &str) -> Result<Vps>;
 
-    fn vps_stop(&mut self, name: &str) -> Result<()>;
+    fn vps_stop(&self, name: &str) -> Result<()>;
 
-    fn vps_start(&mut self, name: &str) -> Result<()>;
+    fn vps_start(&self, name: &str) -> Result<()>;
 
-    fn vps_reset(&mut self, name: &str) -> Result<()>;
+    fn vps_reset(&self, name: &str) -> Result<()>;
 
-    fn vps_set_is_locked(&mut self, name: &str, locked: bool) -> Result<()>;
+    fn vps_set_is_locked(&self, name: &str, locked: bool) -> Result<()>;
 
-    fn vps_set_description(&mut self, name: &str, description: &str) -> Result<()>;
+    fn vps_set_description(&self, name: &str, description: &str) -> Result<()>;
 }
 
 pub trait VpsApi {
     /// [VPS list](https://api.transip.nl/rest/docs.html#vps-vps-get)
-    fn vps_list(&mut self) -> Result<Vec<Vps>>;
+    fn vps_list(&self) -> Result<Vec<Vps>>;
 
-    fn vps(&mut self, name: &str) -> Result<Vps>;
+    /// Lazy version of [`vps_list`](VpsApi::vps_list), that requests one page at a time
+    fn vps_iter(&self) -> Pages<'_, Vps>;
 
-    fn vps_stop(&mut self, name: &str) -> Result<()>;
+    fn vps(&self, name: &str) -> Result<Vps>;
 
-    fn vps_start(&mut self, name: &str) -> Result<()>;
+    fn vps_stop(&self, name: &str) -> Result<()>;
 
-    fn vps_reset(&mut self, name: &str) -> Result<()>;
+    fn vps_start(&self, name: &str) -> Result<()>;
 
-    fn vps_set_is_locked(&mut self, name: &str, locked: bool) -> Result<()>;
+    fn vps_reset(&self, name: &str) -> Result<()>;
 
-    fn vps_set_description(&mut self, name: &str, description: &str) -> Result<()>;
+    fn vps_set_is_locked(&self, name: &str, locked: bool) -> Result<()>;
+
+    fn vps_set_description(&self, name: &str, description: &str) -> Result<()>;
+}
+
+/// Async version of [`VpsApi`]
+#[cfg(feature = "async")]
+pub trait AsyncVpsApi {
+    /// [VPS list](https://api.transip.nl/rest/docs.html#vps-vps-get)
+    fn vps_list(&self) -> impl Future<Output = Result<Vec<Vps>>> + Send;
+
+    fn vps(&self, name: &str) -> impl Future<Output = Result<Vps>> + Send;
+
+    fn vps_stop(&self, name: &str) -> impl Future<Output = Result<()>> + Send;
+
+    fn vps_start(&self, name: &str) -> impl Future<Output = Result<()>> + Send;
+
+    fn vps_reset(&self, name: &str) -> impl Future<Output = Result<()>> + Send;
+
+    fn vps_set_is_locked(
+        &self,
+        name: &str,
+        locked: bool,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn vps_set_description(
+        &self,
+        name: &str,
+        description: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
 }
 
 #[derive(Serialize, Debug)]
@@ -77,10 +109,22 @@ impl Action {
 #[derive(Deserialize, Serialize)]
 pub struct VpsList {
     pub vpss: Vec<Vps>,
-    #[serde(rename = "_links")]
+    #[serde(rename = "_links", default)]
     pub links: Vec<Link>,
 }
 
+impl Page for VpsList {
+    type Item = Vps;
+
+    fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    fn into_items(self) -> Vec<Vps> {
+        self.vpss
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct VpsItem {
     vps: Vps,
@@ -92,7 +136,7 @@ impl From<Vps> for VpsItem {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Vps {
     pub name: String,
@@ -127,12 +171,6 @@ impl HasName for Vps {
     }
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct Link {
-    pub rel: String,
-    pub link: String,
-}
-
 impl UrlVps for Url {
     fn vps_list(&self) -> String {
         format!("{}{}", self.prefix, VPS)
@@ -144,41 +182,82 @@ impl UrlVps for Url {
 }
 
 impl VpsApi for Client {
-    fn vps_list(&mut self) -> Result<Vec<Vps>> {
-        self.get::<VpsList>(&self.url.vps_list())
-            .map(|list| list.vpss)
+    fn vps_list(&self) -> Result<Vec<Vps>> {
+        self.vps_iter().collect()
+    }
+
+    fn vps_iter(&self) -> Pages<'_, Vps> {
+        let url = self.url.vps_list();
+        Pages::new::<VpsList>(self, url)
     }
 
-    fn vps(&mut self, name: &str) -> Result<Vps> {
+    fn vps(&self, name: &str) -> Result<Vps> {
         self.get::<VpsItem>(&self.url.vps(name))
             .map(|item| item.vps)
     }
 
-    fn vps_stop(&mut self, name: &str) -> Result<()> {
+    fn vps_stop(&self, name: &str) -> Result<()> {
         self.patch(&self.url.vps(name), Action::stop())
     }
 
-    fn vps_start(&mut self, name: &str) -> Result<()> {
+    fn vps_start(&self, name: &str) -> Result<()> {
         self.patch(&self.url.vps(name), Action::start())
     }
 
-    fn vps_reset(&mut self, name: &str) -> Result<()> {
+    fn vps_reset(&self, name: &str) -> Result<()> {
         self.patch(&self.url.vps(name), Action::reset())
     }
 
-    fn vps_set_is_locked(&mut self, name: &str, locked: bool) -> Result<()> {
+    fn vps_set_is_locked(&self, name: &str, locked: bool) -> Result<()> {
         let mut vps_item = VpsApi::vps(self, name).map(VpsItem::from)?;
         vps_item.vps.is_customer_locked = locked;
         self.put(&self.url.vps(name), &vps_item)
     }
 
-    fn vps_set_description(&mut self, name: &str, description: &str) -> Result<()> {
+    fn vps_set_description(&self, name: &str, description: &str) -> Result<()> {
         let mut vps_item = VpsApi::vps(self, name).map(VpsItem::from)?;
         description.clone_into(&mut vps_item.vps.description);
         self.put(&self.url.vps(name), &vps_item)
     }
 }
 
+#[cfg(feature = "async")]
+impl AsyncVpsApi for crate::AsyncClient {
+    async fn vps_list(&self) -> Result<Vec<Vps>> {
+        self.get_all::<VpsList>(&self.url.vps_list()).await
+    }
+
+    async fn vps(&self, name: &str) -> Result<Vps> {
+        self.get::<VpsItem>(&self.url.vps(name))
+            .await
+            .map(|item| item.vps)
+    }
+
+    async fn vps_stop(&self, name: &str) -> Result<()> {
+        self.patch(&self.url.vps(name), Action::stop()).await
+    }
+
+    async fn vps_start(&self, name: &str) -> Result<()> {
+        self.patch(&self.url.vps(name), Action::start()).await
+    }
+
+    async fn vps_reset(&self, name: &str) -> Result<()> {
+        self.patch(&self.url.vps(name), Action::reset()).await
+    }
+
+    async fn vps_set_is_locked(&self, name: &str, locked: bool) -> Result<()> {
+        let mut vps_item = AsyncVpsApi::vps(self, name).await.map(VpsItem::from)?;
+        vps_item.vps.is_customer_locked = locked;
+        self.put(&self.url.vps(name), &vps_item).await
+    }
+
+    async fn vps_set_description(&self, name: &str, description: &str) -> Result<()> {
+        let mut vps_item = AsyncVpsApi::vps(self, name).await.map(VpsItem::from)?;
+        description.clone_into(&mut vps_item.vps.description);
+        self.put(&self.url.vps(name), &vps_item).await
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::{Vps, VpsApi};
@@ -186,7 +265,7 @@ mod test {
 
     #[test]
     fn vps_list() {
-        let mut client = Client::demo();
+        let client = Client::fixture("vps");
         let list = client.vps_list().unwrap();
         let vps_names = list.names();
         assert_eq!(
@@ -204,7 +283,7 @@ mod test {
 
     #[test]
     fn vps() {
-        let mut client = Client::demo();
+        let client = Client::fixture("vps");
         let vps = client.vps("transipdemo-vps6").unwrap();
 
         assert_eq!(
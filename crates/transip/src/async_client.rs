@@ -0,0 +1,467 @@
+use core::time::Duration;
+use std::fmt::Debug;
+use std::net::{IpAddr, Ipv6Addr};
+
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+use crate::api::DEFAULT_PAGE_SIZE;
+use crate::authentication::{
+    DEFAULT_REFRESH_MARGIN, KeyPair, MemoryTokenStore, Token, TokenExpired, TokenInfo,
+    TokenResponse, TokenStore, UrlAuthentication, load_token, save_token, token_store_for_path,
+};
+use crate::client::{
+    AGENT_TIMEOUT_SECONDS, TRANSIP_API_PREFIX, USER_AGENT, Url, signed_auth_request, test_mode_url,
+    timeit,
+};
+use crate::{Configuration, Error, Result};
+
+/// AsyncClient is the async counterpart of [`Client`](crate::Client).
+/// It is only available if the `async` feature is enabled.
+/// All methods take `&self`, so a single client can be shared between tasks.
+/// The token is kept behind an async mutex, so concurrent calls wait for a single token refresh.
+pub struct AsyncClient {
+    pub(crate) url: Url,
+    configuration: Box<dyn Configuration>,
+    key: Option<KeyPair>,
+    client: reqwest::Client,
+    token_store: Box<dyn TokenStore>,
+    token: Mutex<Option<Token>>,
+    refresh_margin: Duration,
+    test_mode: bool,
+    page_size: u32,
+}
+
+impl Debug for AsyncClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.url.prefix)
+    }
+}
+
+fn build_client(ipv6only: bool) -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder().timeout(Duration::from_secs(AGENT_TIMEOUT_SECONDS));
+    if ipv6only {
+        builder.local_address(IpAddr::V6(Ipv6Addr::UNSPECIFIED))
+    } else {
+        builder
+    }
+}
+
+impl AsyncClient {
+    pub fn demo() -> Self {
+        Self {
+            url: TRANSIP_API_PREFIX.into(),
+            key: None,
+            client: build_client(false).build().unwrap(),
+            token_store: Box::new(MemoryTokenStore::new()),
+            token: Mutex::new(Some(Token::demo())),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            test_mode: false,
+            page_size: DEFAULT_PAGE_SIZE,
+            configuration: crate::environment::demo_configuration(),
+        }
+    }
+
+    pub fn test(prefix: String) -> Self {
+        Self {
+            url: format!("{prefix}/").as_str().into(),
+            key: None,
+            client: build_client(false).build().unwrap(),
+            token_store: Box::new(MemoryTokenStore::new()),
+            token: Mutex::new(Some(Token::demo())),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            test_mode: false,
+            page_size: DEFAULT_PAGE_SIZE,
+            configuration: crate::environment::demo_configuration(),
+        }
+    }
+}
+
+impl TryFrom<Box<dyn Configuration>> for AsyncClient {
+    type Error = Error;
+    fn try_from(configuration: Box<dyn Configuration>) -> Result<Self> {
+        let key = KeyPair::try_from_setting(
+            configuration.private_key_pem_file(),
+            configuration.private_key_passphrase(),
+        )?;
+        let client = build_client(configuration.ipv6_only())
+            .user_agent(USER_AGENT)
+            .build()?;
+        let token_store = token_store_for_path(configuration.token_path());
+        Ok(Self {
+            url: TRANSIP_API_PREFIX.into(),
+            key: Some(key),
+            client,
+            token: Mutex::new(load_token(token_store.as_ref())),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            test_mode: false,
+            page_size: DEFAULT_PAGE_SIZE,
+            token_store,
+            configuration,
+        })
+    }
+}
+
+impl AsyncClient {
+    /// Replace the store the token is saved in.
+    /// The token in the new store is used if it is still valid.
+    pub fn with_token_store<S>(mut self, token_store: S) -> Self
+    where
+        S: TokenStore + 'static,
+    {
+        if let Some(token) = load_token(&token_store) {
+            *self.token.get_mut() = Some(token);
+        }
+        self.token_store = Box::new(token_store);
+        self
+    }
+}
+
+/// Turns 4xx and 5xx responses into [`Error::Api`], like the blocking client does.
+async fn error_for_status(
+    response: reqwest::Response,
+    method: &str,
+    url: &str,
+) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        let body = response.bytes().await.unwrap_or_default();
+        Err(Error::api(method, url, status.as_u16(), &body))
+    } else {
+        Ok(response)
+    }
+}
+
+impl AsyncClient {
+    /// Information about the token used for the api calls.
+    /// A new token is requested first if there is no valid token.
+    pub async fn token_info(&self) -> Result<TokenInfo> {
+        self.token().await.and_then(|token| token.info())
+    }
+
+    async fn request_token(&self) -> Result<Token> {
+        timeit!(&self.url.auth(), "POST", {
+            let (json, signature, label) =
+                signed_auth_request(self.configuration.as_ref(), self.key.as_ref())?;
+            let response = self
+                .client
+                .post(self.url.auth())
+                .header("Content-Type", "application/json")
+                .header("Signature", signature)
+                .body(json)
+                .send()
+                .await?;
+            let token_response = error_for_status(response, "POST", &self.url.auth())
+                .await?
+                .json::<TokenResponse>()
+                .await?;
+            Token::try_from(token_response.token).map(|token| token.with_label(label))
+        })
+    }
+
+    /// Renew the token when it expires within this margin, 30 seconds by default
+    pub fn with_refresh_margin(mut self, refresh_margin: Duration) -> Self {
+        self.refresh_margin = refresh_margin;
+        self
+    }
+
+    /// In test mode the api validates every request, but does not execute it
+    pub fn with_test_mode(mut self, test_mode: bool) -> Self {
+        self.test_mode = test_mode;
+        self
+    }
+
+    /// Number of items requested per page by the list calls
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub(crate) fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    /// Url of a request, with the test mode parameter added in test mode
+    fn request_url(&self, url: &str) -> String {
+        if self.test_mode {
+            test_mode_url(url)
+        } else {
+            url.to_owned()
+        }
+    }
+
+    /// Returns the authorization header value
+    async fn bearer(&self) -> Result<String> {
+        self.token()
+            .await
+            .map(|token| format!("Bearer {}", token.raw()))
+    }
+
+    /// Returns the token, requesting a new token first if it expires within the refresh margin.
+    /// The lock is held during the refresh, so only one token request is sent at a time.
+    /// If renewal fails while the current token is still valid, the current token is used.
+    async fn token(&self) -> Result<Token> {
+        let mut token = self.token.lock().await;
+        if token.token_expires_within(self.refresh_margin) {
+            match self.request_token().await {
+                Ok(new_token) => {
+                    save_token(self.token_store.as_ref(), &new_token);
+                    *token = Some(new_token);
+                }
+                Err(error) if !token.token_expired() => {
+                    tracing::warn!("Token renewal failed with {}, using current token", error);
+                }
+                Err(error) => {
+                    *token = None;
+                    return Err(error);
+                }
+            }
+        }
+        token.clone().ok_or(Error::Token)
+    }
+
+    #[instrument(skip(self))]
+    pub(crate) async fn get<T>(&self, url: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        timeit!(url, "GET", {
+            let url = &self.request_url(url);
+            let bearer = self.bearer().await?;
+            let response = self
+                .client
+                .get(url)
+                .header("Authorization", bearer)
+                .send()
+                .await?;
+            let result = error_for_status(response, "GET", url)
+                .await?
+                .json::<T>()
+                .await?;
+            Ok(result)
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub(crate) async fn delete<T>(&self, url: &str, object: T) -> Result<()>
+    where
+        T: Serialize + Debug,
+    {
+        timeit!(url, "DELETE", {
+            let url = &self.request_url(url);
+            let bearer = self.bearer().await?;
+            let response = self
+                .client
+                .delete(url)
+                .header("Authorization", bearer)
+                .json(&object)
+                .send()
+                .await?;
+            error_for_status(response, "DELETE", url).await?;
+            Ok(())
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub(crate) async fn delete_no_object(&self, url: &str) -> Result<()> {
+        timeit!(url, "DELETE", {
+            let url = &self.request_url(url);
+            let bearer = self.bearer().await?;
+            let response = self
+                .client
+                .delete(url)
+                .header("Authorization", bearer)
+                .send()
+                .await?;
+            error_for_status(response, "DELETE", url).await?;
+            Ok(())
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub(crate) async fn patch<T>(&self, url: &str, object: T) -> Result<()>
+    where
+        T: Serialize + Debug,
+    {
+        timeit!(url, "PATCH", {
+            let url = &self.request_url(url);
+            let bearer = self.bearer().await?;
+            let response = self
+                .client
+                .patch(url)
+                .header("Authorization", bearer)
+                .json(&object)
+                .send()
+                .await?;
+            error_for_status(response, "PATCH", url).await?;
+            Ok(())
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub(crate) async fn post<T>(&self, url: &str, body: T) -> Result<()>
+    where
+        T: Serialize + Debug,
+    {
+        timeit!(url, "POST", {
+            let url = &self.request_url(url);
+            let bearer = self.bearer().await?;
+            let response = self
+                .client
+                .post(url)
+                .header("Authorization", bearer)
+                .json(&body)
+                .send()
+                .await?;
+            error_for_status(response, "POST", url).await?;
+            Ok(())
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub(crate) async fn put<T>(&self, url: &str, body: T) -> Result<()>
+    where
+        T: Serialize + Debug,
+    {
+        timeit!(url, "PUT", {
+            let url = &self.request_url(url);
+            let bearer = self.bearer().await?;
+            let response = self
+                .client
+                .put(url)
+                .header("Authorization", bearer)
+                .json(&body)
+                .send()
+                .await?;
+            error_for_status(response, "PUT", url).await?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    const DEFAULT_CONTENT_TYPE: &str = "application/json";
+
+    use httpmock::Method::{DELETE, GET, POST};
+
+    use super::AsyncClient;
+    use crate::api::{
+        dns::{AsyncDnsApi, DnsEntry},
+        email::AsyncEmailApi,
+        general::AsyncGeneralApi,
+        vps::AsyncVpsApi,
+    };
+    use crate::authentication::Token;
+
+    #[tokio::test]
+    async fn api_test() {
+        let server = httpmock::MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api-test")
+                    .header("Authorization", format!("Bearer {}", Token::demo().raw()));
+                then.status(200)
+                    .body(r#"{"ping":"pong"}"#)
+                    .header("Content-Type", DEFAULT_CONTENT_TYPE);
+            })
+            .await;
+
+        let client = AsyncClient::test(server.base_url());
+        assert_eq!(client.api_test().await.unwrap(), "pong");
+        mock.assert_calls_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn page_size() {
+        let server = httpmock::MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/vps")
+                    .query_param("page", "1")
+                    .query_param("pageSize", "7");
+                then.status(200)
+                    .body(r#"{"vpss":[]}"#)
+                    .header("Content-Type", DEFAULT_CONTENT_TYPE);
+            })
+            .await;
+
+        let client = AsyncClient::test(server.base_url()).with_page_size(7);
+        assert!(client.vps_list().await.unwrap().is_empty());
+        mock.assert_calls_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn dns_entry_insert() {
+        let server = httpmock::MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/domains/paulmin.nl/dns")
+                    .header("Content-Type", DEFAULT_CONTENT_TYPE)
+                    .body(r#"{"dnsEntry":{"name":"www","expire":300,"type":"A","content":"1.2.3.4"}}"#);
+                then.status(201);
+            })
+            .await;
+
+        let client = AsyncClient::test(server.base_url());
+        let entry = "www 300 A 1.2.3.4".parse::<DnsEntry>().unwrap();
+        client.dns_entry_insert("paulmin.nl", entry).await.unwrap();
+        mock.assert_calls_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn mailbox_delete() {
+        let server = httpmock::MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(DELETE)
+                    .path("/email/paulmin.nl/mailboxes/info@paulmin.nl");
+                then.status(204);
+            })
+            .await;
+
+        let client = AsyncClient::test(server.base_url());
+        client
+            .mailbox_delete("paulmin.nl", "info@paulmin.nl")
+            .await
+            .unwrap();
+        mock.assert_calls_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn error_status() {
+        let server = httpmock::MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api-test");
+                then.status(500).body(r#"{"error":"Internal error"}"#);
+            })
+            .await;
+
+        let client = AsyncClient::test(server.base_url());
+        let error = client.api_test().await.unwrap_err();
+        assert_eq!(error.status(), Some(500));
+        assert_eq!(error.api_message(), Some("Internal error"));
+    }
+
+    #[tokio::test]
+    async fn test_mode() {
+        let server = httpmock::MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api-test").query_param("test", "1");
+                then.status(200)
+                    .body(r#"{"ping":"pong"}"#)
+                    .header("Content-Type", DEFAULT_CONTENT_TYPE);
+            })
+            .await;
+
+        let client = AsyncClient::test(server.base_url()).with_test_mode(true);
+        assert_eq!(client.api_test().await.unwrap(), "pong");
+        mock.assert_calls_async(1).await;
+    }
+}
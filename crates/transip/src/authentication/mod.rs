@@ -7,15 +7,47 @@ use crate::client::Url;
 use serde::Serialize;
 
 pub use key_pair::KeyPair;
-pub use token::{Token, TokenExpired, TokenResponse};
+pub use token::{DEFAULT_REFRESH_MARGIN, Token, TokenExpired, TokenInfo, TokenResponse};
 pub use token_expiration::TokenExpiration;
+#[cfg(feature = "keyring")]
+pub use token_store::KeyringTokenStore;
+pub use token_store::{FileTokenStore, MemoryTokenStore, TokenStore};
 
 mod key_pair;
 mod token;
 mod token_expiration;
+mod token_store;
 
 const AUTH: &str = "auth";
 
+/// Token saved in the store, if it can still be used.
+/// A store that cannot be read is logged and treated as empty.
+pub(crate) fn load_token(token_store: &dyn TokenStore) -> Option<Token> {
+    match token_store.load() {
+        Ok(token) => token.and_then(|raw| Token::try_from(raw).ok()),
+        Err(error) => {
+            tracing::error!("Error {} loading token", error);
+            None
+        }
+    }
+}
+
+/// Saves a refreshed token. Failing to save only means the next run requests a new token.
+pub(crate) fn save_token(token_store: &dyn TokenStore, token: &Token) {
+    if let Err(error) = token_store.save(token.raw()) {
+        tracing::error!("Error {} saving token", error);
+    }
+}
+
+/// File store for a configured token path, memory store if no path is configured
+pub(crate) fn token_store_for_path(token_path: &str) -> Box<dyn TokenStore> {
+    if token_path.is_empty() {
+        Box::new(MemoryTokenStore::new())
+    } else {
+        Box::new(FileTokenStore::new(token_path))
+    }
+}
+
 pub trait UrlAuthentication {
     fn auth(&self) -> String;
 }
@@ -89,6 +121,10 @@ impl AuthRequest {
         }
     }
 
+    pub fn label(&self) -> &str {
+        self.label.as_str()
+    }
+
     pub fn json(&self) -> Vec<u8> {
         serde_json::to_vec(self).unwrap()
     }
@@ -1,34 +1,94 @@
+use core::time::Duration;
+
 use crate::{Error, Result, error::ResultExt};
-use std::{
-    io::{Read, Write},
-    path::Path,
-};
+#[cfg(test)]
+use std::{io::Read, path::Path};
 
 use crate::base64::Base64;
+#[cfg(test)]
 use crate::fs::FileSystem;
-use chrono::Utc;
-use serde::{Deserialize, Serialize};
-use ureq::Body;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
 
 pub const DEMO_TOKEN: &str = include_str!("demo_token.txt");
 
+/// Time before expiry at which a token is renewed, unless configured otherwise
+pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+/// Tokens are never used in the last seconds before they expire, to allow for clock skew
+const EXPIRY_SKEW_SECONDS: i64 = 2;
+
 pub trait TokenExpired {
-    fn token_expired(&self) -> bool;
+    fn token_expired(&self) -> bool {
+        self.token_expires_within(Duration::ZERO)
+    }
+
+    /// The token should be renewed, because it expires within the margin
+    fn token_expires_within(&self, margin: Duration) -> bool;
 }
 
+#[derive(Clone)]
 pub struct Token {
     raw: String,
+    issued: i64,
     expired: i64,
+    label: Option<String>,
 }
 
 impl Token {
     pub fn demo() -> Self {
         Self {
             raw: DEMO_TOKEN.to_owned(),
+            issued: 0,
             expired: i64::MAX,
+            label: None,
         }
     }
 
+    /// Label that was sent with the token request
+    pub fn with_label<S: Into<String>>(mut self, label: S) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Margin before expiry, at most half of the lifetime of the token.
+    /// A token that lives for a minute would otherwise be renewed on every request.
+    fn margin_seconds(&self, margin: Duration) -> i64 {
+        let margin = i64::try_from(margin.as_secs()).unwrap_or(i64::MAX);
+        margin
+            .min(self.expired.saturating_sub(self.issued) / 2)
+            .max(EXPIRY_SKEW_SECONDS)
+    }
+
+    /// Time until the token expires within the margin
+    pub fn refresh_in(&self, margin: Duration) -> Duration {
+        let seconds = self
+            .expired
+            .saturating_sub(self.margin_seconds(margin))
+            .saturating_sub(Utc::now().timestamp());
+        Duration::from_secs(seconds.max(0) as u64)
+    }
+
+    /// Claims of the token
+    pub fn info(&self) -> Result<TokenInfo> {
+        let meta = TokenResponseMeta::try_from(self.raw.as_str())?;
+        Ok(TokenInfo {
+            issued_at: timestamp(meta.iat)?,
+            expires_at: timestamp(meta.exp)?,
+            customer_id: meta.cid,
+            read_only: meta.ro,
+            global_key: meta.gk,
+            label: self.label.clone(),
+        })
+    }
+
+    pub fn raw(&self) -> &str {
+        self.raw.as_str()
+    }
+}
+
+#[cfg(test)]
+impl Token {
     pub fn try_from_reader<R>(mut reader: R) -> Result<Self>
     where
         R: Read,
@@ -38,20 +98,6 @@ impl Token {
         Token::try_from(s)
     }
 
-    pub fn try_to_write<W>(&self, mut writer: W) -> Result<()>
-    where
-        W: Write,
-    {
-        writer.write_all(self.raw.as_bytes()).err_into()
-    }
-
-    pub fn try_to_write_file<P>(&self, path: P) -> Result<()>
-    where
-        P: AsRef<Path>,
-    {
-        path.writer().and_then(|file| self.try_to_write(file))
-    }
-
     pub fn try_from_file<P>(path: P) -> Result<Self>
     where
         P: AsRef<Path>,
@@ -59,39 +105,90 @@ impl Token {
         path.reader().and_then(Token::try_from_reader)
     }
 
-    pub fn raw(&self) -> &str {
-        self.raw.as_str()
+    /// Unsigned token with the claims of a real token, issued an hour before it expires at `exp`
+    pub(crate) fn test_raw(exp: i64) -> String {
+        use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+        let issued = exp - 3600;
+        let claims = serde_json::json!({
+            "iss": "api.transip.nl",
+            "aud": "api.transip.nl",
+            "jti": "test",
+            "iat": issued,
+            "nbf": issued,
+            "exp": exp,
+            "cid": 1000,
+            "ro": false,
+            "gk": true,
+            "kv": true,
+        });
+        format!(
+            "{}.{}.signature",
+            URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"RS512"}"#),
+            URL_SAFE_NO_PAD.encode(claims.to_string()),
+        )
     }
 }
 
 impl TryFrom<String> for Token {
     type Error = Error;
     fn try_from(raw: String) -> Result<Self> {
-        token_expiration_timestamp(raw.clone()).map(|expired| Token { raw, expired })
+        TokenResponseMeta::try_from(raw.as_str()).map(|meta| Token {
+            raw,
+            issued: meta.iat,
+            expired: meta.exp,
+            label: None,
+        })
     }
 }
 
 impl TokenExpired for Token {
-    fn token_expired(&self) -> bool {
-        self.expired < Utc::now().timestamp() + 2
+    fn token_expires_within(&self, margin: Duration) -> bool {
+        self.expired < Utc::now().timestamp() + self.margin_seconds(margin)
     }
 }
 
 impl TokenExpired for Option<Token> {
-    fn token_expired(&self) -> bool {
+    fn token_expires_within(&self, margin: Duration) -> bool {
         if self.is_some() {
-            self.as_ref().unwrap().token_expired()
+            self.as_ref().unwrap().token_expires_within(margin)
         } else {
             true
         }
     }
 }
 
-pub fn token_expiration_timestamp<S>(token: S) -> Result<i64>
-where
-    S: AsRef<str>,
-{
-    TokenResponseMeta::try_from(token.as_ref()).map(|token_meta| token_meta.exp)
+fn timestamp(seconds: i64) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp(seconds, 0).ok_or(Error::Token)
+}
+
+/// Information about the access token the client uses.
+///
+/// Tooling can use this to warn about a read-only or whitelist-restricted token
+/// before attempting a call that modifies something.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenInfo {
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub customer_id: String,
+    /// The token can only be used for reading
+    pub read_only: bool,
+    /// The token can be used from any ip address, not only whitelisted ones
+    pub global_key: bool,
+    /// Label sent with the token request.
+    /// Only known for tokens requested by this process, not for tokens loaded from a store.
+    pub label: Option<String>,
+}
+
+impl TokenInfo {
+    /// The token can only be used from whitelisted ip addresses
+    pub fn whitelisted_only(&self) -> bool {
+        !self.global_key
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -107,21 +204,37 @@ struct TokenResponseMeta {
     pub iat: i64,
     pub nbf: i64,
     pub exp: i64,
-    pub cid: i64,
+    #[serde(deserialize_with = "customer_id")]
+    pub cid: String,
     pub ro: bool,
     pub gk: bool,
     pub kv: bool,
 }
 
+/// The customer id is a number in real tokens and a string in the demo token
+fn customer_id<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum CustomerId {
+        Number(i64),
+        Text(String),
+    }
+    Ok(match CustomerId::deserialize(deserializer)? {
+        CustomerId::Number(number) => number.to_string(),
+        CustomerId::Text(text) => text,
+    })
+}
+
 impl TryFrom<EncodedTokenMeta<'_>> for TokenResponseMeta {
     type Error = Error;
     fn try_from(encoded_token_meta: EncodedTokenMeta) -> Result<Self> {
         encoded_token_meta
             .expiration()
             .base64_decode_url_safe()
-            .map(|data| Body::builder().data(data))
-            .and_then(|mut body| body.read_json().map_err(Into::into))
-            .err_into()
+            .and_then(|data| serde_json::from_slice(&data).err_into())
     }
 }
 
@@ -158,6 +271,7 @@ mod tests {
     // use super::EncodedTokenMeta;
     use super::{Token, TokenExpired};
     use chrono::Utc;
+    use core::time::Duration;
 
     // const RAW_TOKEN: &str = include_str!("/home/paul/transip/expired_token.txt");
     // const TOKEN_META_JSON: &str = include_str!("/home/paul/transip/token_meta.json");
@@ -210,7 +324,9 @@ mod tests {
     fn expired_if_some() {
         let token: Option<Token> = Some(Token {
             raw: Default::default(),
+            issued: 0,
             expired: Utc::now().timestamp(),
+            label: None,
         });
         assert!(token.token_expired());
     }
@@ -219,7 +335,9 @@ mod tests {
     fn not_expired_if_some() {
         let token: Option<Token> = Some(Token {
             raw: Default::default(),
+            issued: 0,
             expired: Utc::now().timestamp() + 10,
+            label: None,
         });
         assert!(!token.token_expired());
     }
@@ -244,4 +362,44 @@ mod tests {
     fn try_demo_token() {
         assert!(!Token::demo().token_expired());
     }
+
+    #[test]
+    fn refresh_margin() {
+        let now = Utc::now().timestamp();
+        let token = Token::try_from(Token::test_raw(now + 600)).unwrap();
+        assert!(!token.token_expires_within(Duration::from_secs(60)));
+        assert!(token.token_expires_within(Duration::from_secs(900)));
+        assert!(token.refresh_in(Duration::from_secs(60)) > Duration::from_secs(500));
+
+        // the margin is at most half of the lifetime of the token
+        let short = Token {
+            raw: Default::default(),
+            issued: now,
+            expired: now + 60,
+            label: None,
+        };
+        assert!(!short.token_expires_within(Duration::from_secs(3600)));
+        assert!(short.refresh_in(Duration::from_secs(3600)) <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn info() {
+        let demo = Token::demo().info().unwrap();
+        assert_eq!(demo.customer_id, "60449");
+        assert!(!demo.read_only);
+        assert!(demo.whitelisted_only());
+        assert!(!demo.is_expired());
+        assert_eq!(demo.label, None);
+
+        let exp = Utc::now().timestamp() + 300;
+        let info = Token::try_from(Token::test_raw(exp))
+            .unwrap()
+            .with_label("test")
+            .info()
+            .unwrap();
+        assert_eq!(info.customer_id, "1000");
+        assert_eq!(info.expires_at.timestamp(), exp);
+        assert!(info.global_key);
+        assert_eq!(info.label.as_deref(), Some("test"));
+    }
 }
@@ -0,0 +1,239 @@
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::Result;
+
+/// Place where the token is kept between runs.
+///
+/// The client loads the token on startup and saves it after every refresh,
+/// so a token is reused until it expires.
+pub trait TokenStore: Send + Sync {
+    /// The saved token, or `None` if no token was saved yet
+    fn load(&self) -> Result<Option<String>>;
+    /// Replace the saved token
+    fn save(&self, token: &str) -> Result<()>;
+}
+
+/// Makes the temporary file of every save unique within the process
+static NEXT_SAVE: AtomicU64 = AtomicU64::new(0);
+
+/// Stores the token in a file.
+///
+/// The token is written to a temporary file first, which then replaces the old file,
+/// so a reader never sees a partially written token.
+/// On unix the file is only readable and writable by the owner.
+#[derive(Clone, Debug)]
+pub struct FileTokenStore {
+    path: PathBuf,
+}
+
+impl FileTokenStore {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    fn temporary_path(&self) -> PathBuf {
+        let mut file_name = self.path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            NEXT_SAVE.fetch_add(1, Ordering::Relaxed)
+        ));
+        self.path.with_file_name(file_name)
+    }
+}
+
+#[cfg(unix)]
+fn create(path: &Path) -> std::io::Result<std::fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn create(path: &Path) -> std::io::Result<std::fs::File> {
+    std::fs::File::create(path)
+}
+
+impl TokenStore for FileTokenStore {
+    fn load(&self) -> Result<Option<String>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(token) => Ok(Some(token.trim().to_owned())),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn save(&self, token: &str) -> Result<()> {
+        let temporary = self.temporary_path();
+        let result = create(&temporary)
+            .and_then(|mut file| {
+                file.write_all(token.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&temporary, &self.path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temporary);
+        }
+        result.map_err(Into::into)
+    }
+}
+
+/// Keeps the token in memory only.
+/// Clones share the same token.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryTokenStore {
+    token: Arc<Mutex<Option<String>>>,
+}
+
+impl MemoryTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The token that was saved last
+    pub fn token(&self) -> Option<String> {
+        self.token
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self) -> Result<Option<String>> {
+        Ok(self.token())
+    }
+
+    fn save(&self, token: &str) -> Result<()> {
+        *self
+            .token
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(token.to_owned());
+        Ok(())
+    }
+}
+
+/// Stores the token in the credential store of the operating system.
+/// Only available if the `keyring` feature is enabled.
+#[cfg(feature = "keyring")]
+pub struct KeyringTokenStore {
+    entry: keyring::Entry,
+}
+
+#[cfg(feature = "keyring")]
+impl KeyringTokenStore {
+    /// Entry for the user name under the service name, like `transip`
+    pub fn new(service: &str, user_name: &str) -> Result<Self> {
+        keyring::Entry::new(service, user_name)
+            .map(|entry| Self { entry })
+            .map_err(|error| crate::Error::TokenStore(error.to_string()))
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl TokenStore for KeyringTokenStore {
+    fn load(&self) -> Result<Option<String>> {
+        match self.entry.get_password() {
+            Ok(token) => Ok(Some(token)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(error) => Err(crate::Error::TokenStore(error.to_string())),
+        }
+    }
+
+    fn save(&self, token: &str) -> Result<()> {
+        self.entry
+            .set_password(token)
+            .map_err(|error| crate::Error::TokenStore(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{FileTokenStore, MemoryTokenStore, TokenStore};
+
+    /// Temporary files of the store that were left behind
+    fn temporary_files(path: &Path) -> Vec<PathBuf> {
+        let prefix = path.file_name().unwrap().to_string_lossy().into_owned();
+        std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|file| {
+                let name = file.file_name().unwrap().to_string_lossy();
+                name.starts_with(&prefix) && name.ends_with(".tmp")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn file() {
+        let path = std::env::temp_dir().join(format!("transip-store-{}.token", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = FileTokenStore::new(&path);
+        assert_eq!(store.load().unwrap(), None);
+
+        store.save("first").unwrap();
+        store.save("second").unwrap();
+        assert_eq!(store.load().unwrap().as_deref(), Some("second"));
+        assert!(temporary_files(&path).is_empty());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn file_saved_concurrently() {
+        let path = std::env::temp_dir().join(format!(
+            "transip-store-concurrent-{}.token",
+            std::process::id()
+        ));
+        let store = FileTokenStore::new(&path);
+        let tokens = (0..8).map(|n| format!("token-{n}")).collect::<Vec<_>>();
+        std::thread::scope(|scope| {
+            for token in &tokens {
+                let store = store.clone();
+                scope.spawn(move || {
+                    for _ in 0..20 {
+                        store.save(token).unwrap();
+                    }
+                });
+            }
+        });
+        assert!(tokens.contains(&store.load().unwrap().unwrap()));
+        assert!(temporary_files(&path).is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn file_in_missing_directory() {
+        let store = FileTokenStore::new("/does/not/exist/token.txt");
+        assert!(store.save("token").is_err());
+    }
+
+    #[test]
+    fn memory() {
+        let store = MemoryTokenStore::new();
+        assert_eq!(store.load().unwrap(), None);
+        store.clone().save("token").unwrap();
+        assert_eq!(store.load().unwrap().as_deref(), Some("token"));
+    }
+}
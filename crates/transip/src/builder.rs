@@ -0,0 +1,580 @@
+use core::time::Duration;
+use std::path::{Path, PathBuf};
+
+use crate::api::DEFAULT_PAGE_SIZE;
+use crate::authentication::{
+    DEFAULT_REFRESH_MARGIN, KeyPair, TokenExpiration, TokenStore, token_store_for_path,
+};
+use crate::client::{AGENT_TIMEOUT_SECONDS, TRANSIP_API_PREFIX, USER_AGENT};
+use crate::transport::Transport;
+use crate::{Client, Configuration, Error, Profile, Result, RetryPolicy};
+
+/// Ip addresses used to connect to the api
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IpFamily {
+    #[default]
+    Any,
+    Ipv4Only,
+    Ipv6Only,
+}
+
+impl IpFamily {
+    pub(crate) fn from_ipv6_only(ipv6_only: bool) -> Self {
+        if ipv6_only { Self::Ipv6Only } else { Self::Any }
+    }
+}
+
+enum PrivateKey {
+    File(PathBuf),
+    Pem(String),
+    Bytes(Vec<u8>),
+}
+
+impl PrivateKey {
+    fn key_pair(&self, passphrase: Option<&str>) -> Result<KeyPair> {
+        match self {
+            Self::File(path) => KeyPair::try_from_file(path, passphrase),
+            Self::Pem(pem) => KeyPair::try_from_pem(pem.as_bytes(), passphrase),
+            Self::Bytes(bytes) => KeyPair::try_from_bytes(bytes, passphrase),
+        }
+    }
+}
+
+/// Configuration collected by the builder
+struct BuilderConfiguration {
+    user_name: String,
+    private_key_pem_file: String,
+    token_path: String,
+    whitelisted_only: bool,
+    read_only: bool,
+    ipv6_only: bool,
+    token_expiration: TokenExpiration,
+}
+
+impl Configuration for BuilderConfiguration {
+    fn user_name(&self) -> &str {
+        self.user_name.as_str()
+    }
+
+    fn private_key_pem_file(&self) -> &str {
+        self.private_key_pem_file.as_str()
+    }
+
+    fn token_path(&self) -> &str {
+        self.token_path.as_str()
+    }
+
+    fn whitelisted_only(&self) -> bool {
+        self.whitelisted_only
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn ipv6_only(&self) -> bool {
+        self.ipv6_only
+    }
+
+    fn token_expiration(&self) -> TokenExpiration {
+        self.token_expiration.clone()
+    }
+}
+
+/// Builds a [`Client`] from programmatic configuration.
+///
+/// All settings are validated by [`build`](ClientBuilder::build),
+/// so configuration errors show up before the first request is made.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use transip::{Client, IpFamily};
+///
+/// let client = Client::builder()
+///     .user_name("example")
+///     .private_key_file("/etc/transip/example.pem")
+///     .token_path("/tmp/transip-token.txt")
+///     .timeout(Duration::from_secs(10))
+///     .ip_family(IpFamily::Ipv6Only)
+///     .read_only(true)
+///     .build()
+///     .expect("Invalid configuration");
+/// ```
+pub struct ClientBuilder {
+    user_name: Option<String>,
+    private_key: Option<PrivateKey>,
+    private_key_passphrase: Option<String>,
+    token_path: Option<PathBuf>,
+    token_store: Option<Box<dyn TokenStore>>,
+    base_url: String,
+    timeout: Duration,
+    ip_family: IpFamily,
+    user_agent: String,
+    read_only: bool,
+    whitelisted_only: bool,
+    token_expiration: TokenExpiration,
+    transport: Option<Box<dyn Transport>>,
+    retry_policy: RetryPolicy,
+    page_size: u32,
+    refresh_margin: Duration,
+    strict_read_only: bool,
+    dry_run: bool,
+    test_mode: bool,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            user_name: None,
+            private_key: None,
+            private_key_passphrase: None,
+            token_path: None,
+            token_store: None,
+            base_url: TRANSIP_API_PREFIX.to_owned(),
+            timeout: Duration::from_secs(AGENT_TIMEOUT_SECONDS),
+            ip_family: IpFamily::default(),
+            user_agent: USER_AGENT.to_owned(),
+            read_only: false,
+            whitelisted_only: false,
+            token_expiration: TokenExpiration::default(),
+            transport: None,
+            retry_policy: RetryPolicy::default(),
+            page_size: DEFAULT_PAGE_SIZE,
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            strict_read_only: false,
+            dry_run: false,
+            test_mode: false,
+        }
+    }
+}
+
+impl TryFrom<Profile> for ClientBuilder {
+    type Error = Error;
+    fn try_from(profile: Profile) -> Result<Self> {
+        let mut builder = Self::default();
+        if let Some(token_expiration) = profile.expiration()? {
+            builder.token_expiration = token_expiration;
+        }
+        builder.user_name = profile.user_name;
+        builder.private_key = profile.private_key.map(|private_key| {
+            let setting = private_key.to_string_lossy();
+            if setting.trim_start().starts_with("-----BEGIN") {
+                PrivateKey::Pem(setting.into_owned())
+            } else {
+                PrivateKey::File(private_key)
+            }
+        });
+        builder.private_key_passphrase = profile.private_key_passphrase;
+        builder.token_path = profile.token_path;
+        builder.read_only = profile.read_only.unwrap_or_default();
+        builder.whitelisted_only = profile.whitelisted_only.unwrap_or_default();
+        builder.ip_family = IpFamily::from_ipv6_only(profile.ipv6_only.unwrap_or_default());
+        Ok(builder)
+    }
+}
+
+fn invalid<S: Into<String>>(message: S) -> Error {
+    Error::Configuration(message.into())
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from a profile of the configuration file.
+    /// Environment variables take precedence over the file,
+    /// and settings made on the builder afterwards take precedence over both.
+    /// See [`Profile::load`] for how the profile is selected.
+    pub fn from_profile(name: Option<&str>) -> Result<Self> {
+        Profile::load(name).and_then(Self::try_from)
+    }
+
+    /// Login name of the Transip account
+    pub fn user_name<S: Into<String>>(mut self, user_name: S) -> Self {
+        self.user_name = Some(user_name.into());
+        self
+    }
+
+    /// Read the private key from a PEM or DER file
+    pub fn private_key_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.private_key = Some(PrivateKey::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Private key in PEM format, like the contents of an environment variable.
+    /// PKCS#1, PKCS#8 and encrypted PKCS#8 keys are accepted.
+    pub fn private_key_pem<S: Into<String>>(mut self, pem: S) -> Self {
+        self.private_key = Some(PrivateKey::Pem(pem.into()));
+        self
+    }
+
+    /// Private key in PEM, PKCS#8 DER or PKCS#1 DER format
+    pub fn private_key_bytes<B: Into<Vec<u8>>>(mut self, bytes: B) -> Self {
+        self.private_key = Some(PrivateKey::Bytes(bytes.into()));
+        self
+    }
+
+    /// Passphrase of an encrypted PKCS#8 private key.
+    /// Decrypting needs the `encrypted-key` feature.
+    pub fn private_key_passphrase<S: Into<String>>(mut self, passphrase: S) -> Self {
+        self.private_key_passphrase = Some(passphrase.into());
+        self
+    }
+
+    /// Keep the token in a [`FileTokenStore`](crate::FileTokenStore) at this path.
+    /// Without a token path or token store the token is only kept in memory.
+    pub fn token_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.token_path = Some(path.as_ref().to_path_buf());
+        self.token_store = None;
+        self
+    }
+
+    /// Keep the token in this store, like a [`KeyringTokenStore`](crate::KeyringTokenStore)
+    pub fn token_store<S: TokenStore + 'static>(mut self, token_store: S) -> Self {
+        self.token_store = Some(Box::new(token_store));
+        self.token_path = None;
+        self
+    }
+
+    /// Url prefix of the api, `https://api.transip.nl/v6` by default
+    pub fn base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Maximum duration of a single request
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn ip_family(mut self, ip_family: IpFamily) -> Self {
+        self.ip_family = ip_family;
+        self
+    }
+
+    pub fn user_agent<S: Into<String>>(mut self, user_agent: S) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Request tokens that can only be used for reading
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Request tokens that can only be used from whitelisted ip addresses
+    pub fn whitelisted_only(mut self, whitelisted_only: bool) -> Self {
+        self.whitelisted_only = whitelisted_only;
+        self
+    }
+
+    pub fn token_expiration(mut self, token_expiration: TokenExpiration) -> Self {
+        self.token_expiration = token_expiration;
+        self
+    }
+
+    /// Send requests through this transport instead of the default `ureq` transport.
+    /// The timeout, ip family and user agent settings are not applied to a custom transport.
+    pub fn transport<T: Transport + 'static>(mut self, transport: T) -> Self {
+        self.transport = Some(Box::new(transport));
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Number of items requested per page by the list calls
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Renew the token when it expires within this margin
+    pub fn refresh_margin(mut self, refresh_margin: Duration) -> Self {
+        self.refresh_margin = refresh_margin;
+        self
+    }
+
+    /// Reject requests that modify something without sending them
+    pub fn strict_read_only(mut self, strict_read_only: bool) -> Self {
+        self.strict_read_only = strict_read_only;
+        self
+    }
+
+    /// Record requests that modify something instead of sending them
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Let the api validate requests without executing them
+    pub fn test_mode(mut self, test_mode: bool) -> Self {
+        self.test_mode = test_mode;
+        self
+    }
+
+    /// Validates the configuration and creates the client
+    pub fn build(self) -> Result<Client> {
+        let user_name = self
+            .user_name
+            .filter(|user_name| !user_name.trim().is_empty())
+            .ok_or_else(|| invalid("user name is missing"))?;
+        let key = self
+            .private_key
+            .as_ref()
+            .ok_or_else(|| invalid("private key is missing"))?
+            .key_pair(self.private_key_passphrase.as_deref())?;
+        let base_url = self.base_url.trim_end_matches('/');
+        if !(base_url.starts_with("https://") || base_url.starts_with("http://")) {
+            return Err(invalid(format!(
+                "base url {base_url} should start with https:// or http://"
+            )));
+        }
+        if self.timeout.is_zero() {
+            return Err(invalid("timeout should be more than zero"));
+        }
+        if self.user_agent.is_empty()
+            || !self
+                .user_agent
+                .chars()
+                .all(|c| c.is_ascii() && !c.is_ascii_control())
+        {
+            return Err(invalid("user agent should be printable ascii"));
+        }
+        if self.page_size == 0 {
+            return Err(invalid("page size should be more than zero"));
+        }
+        if let Some(directory) = self
+            .token_path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .filter(|directory| !directory.as_os_str().is_empty())
+            && !directory.is_dir()
+        {
+            return Err(invalid(format!(
+                "token directory {} does not exist",
+                directory.display()
+            )));
+        }
+
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => default_transport(self.ip_family, self.timeout, &self.user_agent)?,
+        };
+        let token_path = self
+            .token_path
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let token_store = self
+            .token_store
+            .unwrap_or_else(|| token_store_for_path(&token_path));
+        let configuration = BuilderConfiguration {
+            user_name,
+            private_key_pem_file: match &self.private_key {
+                Some(PrivateKey::File(path)) => path.to_string_lossy().into_owned(),
+                _ => String::default(),
+            },
+            token_path,
+            whitelisted_only: self.whitelisted_only,
+            read_only: self.read_only,
+            ipv6_only: self.ip_family == IpFamily::Ipv6Only,
+            token_expiration: self.token_expiration,
+        };
+        Ok(Client::new(
+            format!("{base_url}/").as_str().into(),
+            Box::new(configuration),
+            Some(key),
+            transport,
+            token_store,
+        )
+        .with_retry_policy(self.retry_policy)
+        .with_page_size(self.page_size)
+        .with_refresh_margin(self.refresh_margin)
+        .with_strict_read_only(self.strict_read_only)
+        .with_dry_run(self.dry_run)
+        .with_test_mode(self.test_mode))
+    }
+}
+
+#[cfg(feature = "ureq")]
+fn default_transport(
+    ip_family: IpFamily,
+    timeout: Duration,
+    user_agent: &str,
+) -> Result<Box<dyn Transport>> {
+    Ok(Box::new(crate::client::default_transport(
+        ip_family, timeout, user_agent,
+    )))
+}
+
+#[cfg(not(feature = "ureq"))]
+fn default_transport(_: IpFamily, _: Duration, _: &str) -> Result<Box<dyn Transport>> {
+    Err(invalid(
+        "transport is missing, enable the ureq feature or set a transport",
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+
+    use super::{ClientBuilder, IpFamily};
+    use crate::api::general::GeneralApi;
+    use crate::authentication::Token;
+    use crate::transport::{MemoryTransport, Method, Response};
+    use crate::{Client, Error, MemoryTokenStore, Profile, TokenExpiration, TokenStore};
+
+    const KEY: &str = include_str!("../fixtures/test-key.pem");
+
+    fn transport() -> MemoryTransport {
+        let token = Token::test_raw(Utc::now().timestamp() + 300);
+        MemoryTransport::default()
+            .respond(
+                Method::Post,
+                "/auth",
+                Response::json(201, format!(r#"{{"token":"{token}"}}"#)),
+            )
+            .respond(
+                Method::Get,
+                "/api-test",
+                Response::json(200, r#"{"ping":"pong"}"#),
+            )
+    }
+
+    fn transport_without_auth() -> MemoryTransport {
+        MemoryTransport::default().respond(
+            Method::Get,
+            "/api-test",
+            Response::json(200, r#"{"ping":"pong"}"#),
+        )
+    }
+
+    fn configuration_error(builder: ClientBuilder) -> String {
+        match builder.build() {
+            Err(Error::Configuration(message)) => message,
+            Err(error) => panic!("Unexpected error {error}"),
+            Ok(_) => panic!("Invalid configuration accepted"),
+        }
+    }
+
+    #[test]
+    fn build() {
+        let transport = transport();
+        let client = Client::builder()
+            .user_name("example")
+            .private_key_pem(KEY)
+            .base_url("https://fake.example.com/v6/")
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        assert_eq!(client.api_test().unwrap(), "pong");
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].url, "https://fake.example.com/v6/auth");
+        let auth =
+            serde_json::from_slice::<serde_json::Value>(requests[0].body.as_deref().unwrap())
+                .unwrap();
+        assert_eq!(auth["login"], "example");
+        assert_eq!(auth["read_only"], false);
+        assert_eq!(requests[1].url, "https://fake.example.com/v6/api-test");
+    }
+
+    #[test]
+    fn private_key_bytes() {
+        let builder = || {
+            Client::builder()
+                .user_name("example")
+                .transport(transport())
+        };
+        assert!(builder().private_key_bytes(KEY).build().is_ok());
+        assert!(matches!(
+            builder().private_key_bytes(b"no key".to_vec()).build(),
+            Err(Error::Rejected(_))
+        ));
+        assert!(matches!(
+            builder().private_key_pem("no key").build(),
+            Err(Error::Key(_))
+        ));
+    }
+
+    #[test]
+    fn missing_settings() {
+        assert_eq!(
+            configuration_error(Client::builder().private_key_pem(KEY)),
+            "user name is missing"
+        );
+        assert_eq!(
+            configuration_error(Client::builder().user_name("example")),
+            "private key is missing"
+        );
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let builder = || {
+            Client::builder()
+                .user_name("example")
+                .private_key_pem(KEY)
+                .transport(transport())
+        };
+        assert!(configuration_error(builder().base_url("api.transip.nl")).starts_with("base url"));
+        assert!(
+            configuration_error(builder().timeout(std::time::Duration::ZERO))
+                .starts_with("timeout")
+        );
+        assert!(configuration_error(builder().user_agent("a\nb")).starts_with("user agent"));
+        assert!(configuration_error(builder().page_size(0)).starts_with("page size"));
+        assert!(
+            configuration_error(builder().token_path("/does/not/exist/token.txt"))
+                .starts_with("token directory")
+        );
+    }
+
+    #[test]
+    fn profile_overrides() {
+        let profile = Profile {
+            user_name: Some("profile".to_owned()),
+            private_key: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/test-key.pem").into()),
+            read_only: Some(true),
+            ipv6_only: Some(true),
+            token_expiration: Some("1 hour".to_owned()),
+            ..Default::default()
+        };
+        let builder = ClientBuilder::try_from(profile).unwrap().read_only(false);
+        assert_eq!(builder.user_name.as_deref(), Some("profile"));
+        assert!(!builder.read_only);
+        assert_eq!(builder.ip_family, IpFamily::Ipv6Only);
+        assert_eq!(builder.token_expiration, TokenExpiration::Hours(1));
+        assert!(builder.transport(transport()).build().is_ok());
+    }
+
+    #[test]
+    fn token_store() {
+        let store = MemoryTokenStore::new();
+        let client = || {
+            Client::builder()
+                .user_name("example")
+                .private_key_pem(KEY)
+                .token_store(store.clone())
+        };
+
+        let transport = transport();
+        let first = client().transport(transport.clone()).build().unwrap();
+        first.api_test().unwrap();
+        let token = store.token().expect("refreshed token is saved");
+        assert_eq!(transport.requests().len(), 2);
+
+        let transport = transport_without_auth();
+        let second = client().transport(transport.clone()).build().unwrap();
+        second.api_test().unwrap();
+        assert_eq!(store.load().unwrap(), Some(token));
+        assert_eq!(transport.requests().len(), 1);
+    }
+}
@@ -1,20 +1,31 @@
 use core::time::Duration;
 use std::fmt::Debug;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use serde::{Serialize, de::DeserializeOwned};
 use tracing::instrument;
-use ureq::Agent;
-use ureq::config::{ConfigBuilder, IpFamily};
-use ureq::typestate::AgentScope;
 
+#[cfg(feature = "ureq")]
+use crate::IpFamily;
+use crate::api::DEFAULT_PAGE_SIZE;
 use crate::authentication::{
-    AuthRequest, KeyPair, Token, TokenExpired, TokenResponse, UrlAuthentication,
+    AuthRequest, DEFAULT_REFRESH_MARGIN, KeyPair, Token, TokenExpired, TokenInfo, TokenResponse,
+    TokenStore, UrlAuthentication, load_token, save_token, token_store_for_path,
 };
-use crate::{Configuration, Error, Result};
+use crate::transport::{Method, RecordingTransport, Request, Response, Transport};
+use crate::{ClientBuilder, Configuration, Error, PlannedChange, RateLimit, Result, RetryPolicy};
 
-const TRANSIP_API_PREFIX: &str = "https://api.transip.nl/v6/";
-const AGENT_TIMEOUT_SECONDS: u64 = 30;
-const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+pub(crate) const TRANSIP_API_PREFIX: &str = "https://api.transip.nl/v6/";
+pub(crate) const AGENT_TIMEOUT_SECONDS: u64 = 30;
+pub(crate) const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+/// Query parameter that makes the api validate a request without executing it
+const TEST_MODE_PARAMETER: &str = "test=1";
+
+/// Url with the test mode parameter added to the query
+pub(crate) fn test_mode_url(url: &str) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}{TEST_MODE_PARAMETER}")
+}
 
 macro_rules! timeit {
     ($url:expr_2021, $method:expr_2021, $code:block) => {{
@@ -39,7 +50,10 @@ macro_rules! timeit {
     }};
 }
 
-#[derive(Debug)]
+#[cfg(feature = "async")]
+pub(crate) use timeit;
+
+#[derive(Clone, Debug)]
 pub struct Url {
     pub prefix: String,
 }
@@ -56,13 +70,25 @@ impl From<&str> for Url {
 /// After creation of a client, this client can be used to call a Transip API call.
 /// Each call starts with a check to see if we have a valid JWT token
 /// If the token is expired or non existant then the Transip API call for requesting a new token is called
-/// Tokens are persisted to disk on exit and reused if not expired on application startup
+/// Tokens are saved in the [`TokenStore`] after every refresh and reused if not expired on application startup
+///
+/// A client is a cheap handle that can be cloned and shared between threads.
+/// Clones share the token, so concurrent calls wait for a single token refresh.
+#[derive(Clone)]
 pub struct Client {
     pub(crate) url: Url,
-    configuration: Box<dyn Configuration>,
-    key: Option<KeyPair>,
-    agent: Agent,
-    token: Option<Token>,
+    configuration: Arc<dyn Configuration>,
+    key: Option<Arc<KeyPair>>,
+    transport: Arc<dyn Transport>,
+    token_store: Arc<dyn TokenStore>,
+    token: Arc<Mutex<Option<Token>>>,
+    retry_policy: RetryPolicy,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+    page_size: u32,
+    refresh_margin: Duration,
+    strict_read_only: bool,
+    planned_changes: Option<Arc<Mutex<Vec<PlannedChange>>>>,
+    test_mode: bool,
 }
 
 impl Debug for Client {
@@ -73,33 +99,79 @@ impl Debug for Client {
 
 // #[cfg(test)]
 impl Client {
+    /// Client using the demo token, that replays a fixture from the fixtures directory
+    #[cfg(test)]
+    pub(crate) fn fixture(name: &str) -> Self {
+        let path = format!("{}/fixtures/{name}.json", env!("CARGO_MANIFEST_DIR"));
+        let transport = crate::transport::ReplayTransport::try_from_file(path).unwrap();
+        Self::test_with_transport(
+            TRANSIP_API_PREFIX.trim_end_matches('/').to_owned(),
+            transport,
+        )
+    }
+
+    #[cfg(feature = "ureq")]
     pub fn demo() -> Self {
-        Self {
-            url: TRANSIP_API_PREFIX.into(),
-            key: None,
-            agent: Agent::config_builder()
-                .timeout_global(Some(Duration::from_secs(AGENT_TIMEOUT_SECONDS)))
-                //                .user_agent(USER_AGENT)
-                .build()
-                .into(),
-            token: Some(Token::demo()),
-            configuration: crate::environment::demo_configuration(),
-        }
+        Self::test(TRANSIP_API_PREFIX.trim_end_matches('/').to_owned())
     }
 
+    #[cfg(feature = "ureq")]
     pub fn test(prefix: String) -> Self {
+        let transport = default_transport(
+            IpFamily::Any,
+            core::time::Duration::from_secs(AGENT_TIMEOUT_SECONDS),
+            USER_AGENT,
+        );
+        Self::test_with_transport(prefix, transport)
+    }
+
+    /// Client using the demo token, that sends all requests through the transport
+    pub fn test_with_transport<T>(prefix: String, transport: T) -> Self
+    where
+        T: Transport + 'static,
+    {
+        let client = Self::new(
+            format!("{prefix}/").as_str().into(),
+            crate::environment::demo_configuration(),
+            None,
+            Box::new(transport),
+            Box::new(crate::MemoryTokenStore::new()),
+        );
+        *lock(&client.token) = Some(Token::demo());
+        client
+    }
+}
+
+impl Client {
+    pub(crate) fn new(
+        url: Url,
+        configuration: Box<dyn Configuration>,
+        key: Option<KeyPair>,
+        transport: Box<dyn Transport>,
+        token_store: Box<dyn TokenStore>,
+    ) -> Self {
+        let token = load_token(token_store.as_ref());
         Self {
-            url: format!("{prefix}/").as_str().into(),
-            key: None,
-            agent: Agent::config_builder()
-                .timeout_global(Some(Duration::from_secs(AGENT_TIMEOUT_SECONDS)))
-                //                .user_agent(USER_AGENT)
-                .build()
-                .into(),
-            token: Some(Token::demo()),
-            configuration: crate::environment::demo_configuration(),
+            url,
+            configuration: configuration.into(),
+            key: key.map(Arc::new),
+            transport: transport.into(),
+            token_store: token_store.into(),
+            token: Arc::new(Mutex::new(token)),
+            retry_policy: RetryPolicy::default(),
+            rate_limit: Arc::new(Mutex::new(None)),
+            page_size: DEFAULT_PAGE_SIZE,
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            strict_read_only: false,
+            planned_changes: None,
+            test_mode: false,
         }
     }
+
+    /// Builder for a client with programmatic configuration
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
 }
 
 //#[derive(Debug)]
@@ -125,166 +197,777 @@ impl Client {
 //    }
 //}
 
-fn build_agent(ipv6only: bool) -> ConfigBuilder<AgentScope> {
-    if ipv6only {
-        Agent::config_builder().ip_family(IpFamily::Ipv6Only)
-    } else {
-        Agent::config_builder().ip_family(IpFamily::Any)
-    }
+#[cfg(feature = "ureq")]
+pub(crate) fn default_transport(
+    ip_family: IpFamily,
+    timeout: core::time::Duration,
+    user_agent: &str,
+) -> crate::transport::UreqTransport {
+    crate::transport::UreqTransport::config_builder(ip_family)
+        .timeout_global(Some(timeout))
+        .user_agent(user_agent)
+        .build()
+        .new_agent()
+        .into()
 }
 
+#[cfg(feature = "ureq")]
 impl TryFrom<Box<dyn Configuration>> for Client {
     type Error = Error;
     fn try_from(configuration: Box<dyn Configuration>) -> Result<Self> {
-        KeyPair::try_from_file(configuration.private_key_pem_file()).map(|key| Self {
-            url: TRANSIP_API_PREFIX.into(),
-            key: Some(key),
-            agent: build_agent(configuration.ipv6_only())
-                .user_agent(USER_AGENT)
-                .build()
-                .into(),
-            token: Token::try_from_file(configuration.token_path()).ok(),
+        let transport = default_transport(
+            IpFamily::from_ipv6_only(configuration.ipv6_only()),
+            core::time::Duration::from_secs(AGENT_TIMEOUT_SECONDS),
+            USER_AGENT,
+        );
+        Self::try_with_transport(configuration, transport)
+    }
+}
+
+/// Locks the mutex, also if another thread panicked while holding it
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Creates the json body, signature and label for a token request.
+/// Shared between the blocking [`Client`] and the async client.
+pub(crate) fn signed_auth_request(
+    configuration: &dyn Configuration,
+    key: Option<&KeyPair>,
+) -> Result<(Vec<u8>, String, String)> {
+    let auth_request = AuthRequest::new(
+        configuration.user_name(),
+        configuration.token_expiration(),
+        configuration.read_only(),
+        configuration.whitelisted_only(),
+    );
+    let json = auth_request.json();
+    let signature = key.ok_or(Error::Key("None"))?.sign(&json)?;
+    Ok((json, signature, auth_request.label().to_owned()))
+}
+
+impl Client {
+    /// Create a client that sends all requests through the transport.
+    /// Use this on targets where the default `ureq` transport is not available.
+    pub fn try_with_transport<T>(
+        configuration: Box<dyn Configuration>,
+        transport: T,
+    ) -> Result<Self>
+    where
+        T: Transport + 'static,
+    {
+        let key = KeyPair::try_from_setting(
+            configuration.private_key_pem_file(),
+            configuration.private_key_passphrase(),
+        )?;
+        let token_store = token_store_for_path(configuration.token_path());
+        Ok(Self::new(
+            TRANSIP_API_PREFIX.into(),
             configuration,
+            Some(key),
+            Box::new(transport),
+            token_store,
+        ))
+    }
+
+    /// Replace the store the token is saved in.
+    /// The token in the new store is used if it is still valid.
+    pub fn with_token_store<S>(mut self, token_store: S) -> Self
+    where
+        S: TokenStore + 'static,
+    {
+        if let Some(token) = load_token(&token_store) {
+            *lock(&self.token) = Some(token);
+        }
+        self.token_store = Arc::new(token_store);
+        self
+    }
+
+    /// Replace the transport used to send requests
+    pub fn with_transport<T>(mut self, transport: T) -> Self
+    where
+        T: Transport + 'static,
+    {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Write all requests and responses of this client to a fixture file.
+    /// See [`RecordingTransport`](crate::transport::RecordingTransport).
+    pub fn with_recording<P>(mut self, path: P) -> Self
+    where
+        P: AsRef<std::path::Path>,
+    {
+        self.transport = Arc::new(RecordingTransport::new(self.transport, path));
+        self
+    }
+
+    /// Replace the policy used to retry failed requests
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Number of items requested per page by the list calls
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Renew the token when it expires within this margin, 30 seconds by default.
+    /// The margin is at most half of the lifetime of the token.
+    pub fn with_refresh_margin(mut self, refresh_margin: Duration) -> Self {
+        self.refresh_margin = refresh_margin;
+        self
+    }
+
+    /// Reject every request that would modify something with [`Error::ReadOnly`],
+    /// without sending it. A read-only token only makes the api reject them.
+    pub fn with_strict_read_only(mut self, strict_read_only: bool) -> Self {
+        self.strict_read_only = strict_read_only;
+        self
+    }
+
+    /// In dry-run mode requests that would modify something are logged and recorded,
+    /// but not sent. Reading still uses the api.
+    /// The recorded requests are returned by [`Client::planned_changes`].
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.planned_changes = dry_run.then(Default::default);
+        self
+    }
+
+    /// In test mode the api validates every request, but does not execute it.
+    /// A request that would fail returns the same error, but nothing is changed.
+    pub fn with_test_mode(mut self, test_mode: bool) -> Self {
+        self.test_mode = test_mode;
+        self
+    }
+
+    /// Requests recorded in dry-run mode, in the order they were made.
+    /// Clones of the client share the recorded requests.
+    pub fn planned_changes(&self) -> Vec<PlannedChange> {
+        self.planned_changes
+            .as_ref()
+            .map(|planned_changes| lock(planned_changes).clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    /// Request quota reported by the last api response.
+    /// Batch jobs can use this to throttle themselves.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        lock(&self.rate_limit).clone()
+    }
+
+    /// Information about the token used for the api calls.
+    /// A new token is requested first if there is no valid token.
+    pub fn token_info(&self) -> Result<TokenInfo> {
+        self.token().and_then(|token| token.info())
+    }
+
+    /// Returns the authorization header value
+    fn bearer(&self) -> Result<String> {
+        self.token().map(|token| format!("Bearer {}", token.raw()))
+    }
+
+    /// Returns the token, requesting a new token first if it expires within the refresh margin.
+    /// The lock is held during the refresh, so only one token request is sent at a time.
+    /// If renewal fails while the current token is still valid, the current token is used
+    /// and the next call tries again.
+    pub(crate) fn token(&self) -> Result<Token> {
+        let mut token = lock(&self.token);
+        if token.token_expires_within(self.refresh_margin) {
+            match self.request_token() {
+                Ok(new_token) => {
+                    save_token(self.token_store.as_ref(), &new_token);
+                    *token = Some(new_token);
+                }
+                Err(error) if !token.token_expired() => {
+                    tracing::warn!("Token renewal failed with {}, using current token", error);
+                }
+                Err(error) => {
+                    *token = None;
+                    return Err(error);
+                }
+            }
+        }
+        token.clone().ok_or(Error::Token)
+    }
+
+    /// Request a new token, also if the current token is still valid
+    pub fn refresh_token(&self) -> Result<TokenInfo> {
+        let mut token = lock(&self.token);
+        let new_token = self.request_token()?;
+        save_token(self.token_store.as_ref(), &new_token);
+        let info = new_token.info();
+        *token = Some(new_token);
+        info
+    }
+
+    /// Time until the token should be renewed, zero if there is no valid token
+    pub(crate) fn token_refresh_in(&self) -> Duration {
+        lock(&self.token)
+            .as_ref()
+            .map(|token| token.refresh_in(self.refresh_margin))
+            .unwrap_or_default()
+    }
+
+    fn request_token(&self) -> Result<Token> {
+        let span = tracing::span!(tracing::Level::INFO, "token_refresh");
+        let _span_enter = span.enter();
+        timeit!(&self.url.auth(), "POST", {
+            let (json, signature, label) =
+                signed_auth_request(self.configuration.as_ref(), self.key.as_deref())?;
+            let request = Request::new(Method::Post, self.url.auth())
+                .header("Content-Type", "application/json")
+                .header("Signature", signature)
+                .body(json);
+            let token_response = self
+                .transport
+                .send(request.clone())?
+                .error_for_status(&request)?
+                .read_json::<TokenResponse>()?;
+            Token::try_from(token_response.token).map(|token| token.with_label(label))
         })
     }
-}
 
-impl Drop for Client {
-    fn drop(&mut self) {
-        if self.key.is_some()
-            && let Some(token) = self.token.take()
-            && let Err(error) = token.try_to_write_file(self.configuration.token_path())
-        {
-            tracing::error!(
-                "Error {} writing token to {}",
-                error,
-                self.configuration.token_path()
-            );
+    /// Sends a request, retrying it according to the retry policy.
+    /// The rate limit headers of every response are remembered.
+    fn execute(&self, mut request: Request) -> Result<Response> {
+        if self.test_mode {
+            request.url = test_mode_url(&request.url);
+        }
+        let mut attempt = 1;
+        loop {
+            let bearer = self.bearer()?;
+            let result = self
+                .transport
+                .send(request.clone().header("Authorization", bearer))
+                .and_then(|response| {
+                    if let Some(rate_limit) = RateLimit::from_response(&response) {
+                        *lock(&self.rate_limit) = Some(rate_limit);
+                    }
+                    response.error_for_status(&request)
+                });
+            match result {
+                Err(error) => {
+                    let rate_limit = self.rate_limit();
+                    match self.retry_policy.delay(
+                        attempt,
+                        request.method,
+                        &error,
+                        rate_limit.as_ref(),
+                    ) {
+                        Some(delay) => {
+                            tracing::warn!(
+                                "{} {} failed with {}, retry in {} milliseconds",
+                                request.method,
+                                request.url,
+                                error,
+                                delay.as_millis()
+                            );
+                            std::thread::sleep(delay);
+                            attempt += 1;
+                        }
+                        None => return Err(error),
+                    }
+                }
+                Ok(response) => return Ok(response),
+            }
         }
     }
-}
 
-impl Client {
-    fn refresh_token_if_needed(&mut self) -> Result<()> {
-        if self.token.token_expired() {
-            let span = tracing::span!(tracing::Level::INFO, "token_refresh");
-            let _span_enter = span.enter();
-            let token_result = timeit!(&self.url.auth(), "POST", {
-                let auth_request = AuthRequest::new(
-                    self.configuration.user_name(),
-                    self.configuration.token_expiration(),
-                    self.configuration.read_only(),
-                    self.configuration.whitelisted_only(),
-                );
-                let json = auth_request.json();
-                let signature = self.key.as_ref().unwrap().sign(&json)?;
-                let token_response = self
-                    .agent
-                    .post(&self.url.auth())
-                    .header("Content-Type", "application/json")
-                    .header("Signature", &signature)
-                    .send_json(&auth_request)?
-                    .into_body()
-                    .read_json::<TokenResponse>()?;
-                Token::try_from(token_response.token)
+    /// Sends a request that modifies something, unless strict read-only or dry-run mode is enabled
+    fn mutate(&self, request: Request) -> Result<()> {
+        if self.strict_read_only {
+            return Err(Error::ReadOnly {
+                method: request.method.to_string(),
+                url: request.url,
             });
-            self.token = token_result.ok();
+        }
+        match self.planned_changes.as_ref() {
+            Some(planned_changes) => {
+                let planned_change = PlannedChange::from(&request);
+                tracing::info!("dry run {}", planned_change);
+                lock(planned_changes).push(planned_change);
+            }
+            None => {
+                self.execute(request)?;
+            }
         }
         Ok(())
     }
 
     #[instrument(skip(self))]
-    pub(crate) fn get<T>(&mut self, url: &str) -> Result<T>
+    pub(crate) fn get<T>(&self, url: &str) -> Result<T>
     where
         T: DeserializeOwned,
     {
         timeit!(url, "GET", {
-            self.refresh_token_if_needed()?;
-            let token = self.token.as_ref().ok_or(Error::Token)?;
-            let response = self
-                .agent
-                .get(url)
-                .header("Authorization", &format!("Bearer {}", token.raw()))
-                .call()?;
-            let result = response.into_body().read_json::<T>()?;
-            Ok(result)
+            self.execute(Request::new(Method::Get, url))?
+                .read_json::<T>()
         })
     }
 
     #[instrument(skip(self))]
-    pub(crate) fn delete<T>(&mut self, url: &str, object: T) -> Result<()>
+    pub(crate) fn delete<T>(&self, url: &str, object: T) -> Result<()>
     where
         T: Serialize + Debug,
     {
         timeit!(url, "DELETE", {
-            self.refresh_token_if_needed()?;
-            let token = self.token.as_ref().ok_or(Error::Token)?;
-            self.agent
-                .delete(url)
-                .header("Authorization", &format!("Bearer {}", token.raw()))
-                .call()?;
-            Ok(())
+            self.mutate(Request::new(Method::Delete, url).json(&object)?)
         })
     }
 
     #[instrument(skip(self))]
-    pub(crate) fn delete_no_object(&mut self, url: &str) -> Result<()> {
+    pub(crate) fn delete_no_object(&self, url: &str) -> Result<()> {
         timeit!(url, "DELETE", {
-            self.refresh_token_if_needed()?;
-            let token = self.token.as_ref().ok_or(Error::Token)?;
-            self.agent
-                .delete(url)
-                .header("Authorization", &format!("Bearer {}", token.raw()))
-                .call()?;
-            Ok(())
+            self.mutate(Request::new(Method::Delete, url))
         })
     }
 
     #[instrument(skip(self))]
-    pub(crate) fn patch<T>(&mut self, url: &str, object: T) -> Result<()>
+    pub(crate) fn patch<T>(&self, url: &str, object: T) -> Result<()>
     where
         T: Serialize + Debug,
     {
         timeit!(url, "PATCH", {
-            self.refresh_token_if_needed()?;
-            let token = self.token.as_ref().ok_or(Error::Token)?;
-            self.agent
-                .patch(url)
-                .header("Authorization", &format!("Bearer {}", token.raw()))
-                .send_json(object)?;
-            Ok(())
+            self.mutate(Request::new(Method::Patch, url).json(&object)?)
         })
     }
 
     #[instrument(skip(self))]
-    pub(crate) fn post<T>(&mut self, url: &str, body: T) -> Result<()>
+    pub(crate) fn post<T>(&self, url: &str, body: T) -> Result<()>
     where
         T: Serialize + Debug,
     {
         timeit!(url, "POST", {
-            self.refresh_token_if_needed()?;
-            let token = self.token.as_ref().ok_or(Error::Token)?;
-            self.agent
-                .post(url)
-                .header("Authorization", &format!("Bearer {}", token.raw()))
-                .send_json(body)?;
-            Ok(())
+            self.mutate(Request::new(Method::Post, url).json(&body)?)
         })
     }
 
     #[instrument(skip(self))]
-    pub(crate) fn put<T>(&mut self, url: &str, body: T) -> Result<()>
+    pub(crate) fn put<T>(&self, url: &str, body: T) -> Result<()>
     where
         T: Serialize + Debug,
     {
         timeit!(url, "PUT", {
-            self.refresh_token_if_needed()?;
-            let token = self.token.as_ref().ok_or(Error::Token)?;
-            self.agent
-                .put(url)
-                .header("Authorization", &format!("Bearer {}", token.raw()))
-                .send_json(body)?;
-            Ok(())
+            self.mutate(Request::new(Method::Put, url).json(&body)?)
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    #[cfg(feature = "ureq")]
+    use httpmock::Method::{DELETE, GET, POST};
+
+    use super::Client;
+    use crate::RetryPolicy;
+    use crate::api::{
+        dns::{DnsApi, DnsEntry},
+        domain::DomainApi,
+        general::GeneralApi,
+        vps::VpsApi,
+    };
+    use crate::authentication::Token;
+    use crate::transport::{MemoryTransport, Method, Response};
+
+    fn fast_retries() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn error_not_found() {
+        let server = httpmock::MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/domains/example.com");
+            then.status(404)
+                .header("Content-Type", "application/json")
+                .body(r#"{"error":"Domain with name 'example.com' not found"}"#);
+        });
+
+        let client = Client::test(server.base_url());
+        let error = client.domain_item("example.com").unwrap_err();
+        assert!(error.is_not_found());
+        assert_eq!(
+            error.api_message(),
+            Some("Domain with name 'example.com' not found")
+        );
+        mock.assert_calls(1);
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn error_conflict() {
+        let server = httpmock::MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/domains/example.com/dns");
+            then.status(409)
+                .header("Content-Type", "application/json")
+                .body(r#"{"error":"This DNS entry already exists"}"#);
+        });
+
+        let client = Client::test(server.base_url());
+        let entry = "www 300 A 1.2.3.4".parse::<DnsEntry>().unwrap();
+        let error = client.dns_entry_insert("example.com", entry).unwrap_err();
+        assert!(error.is_conflict());
+        assert!(
+            error
+                .to_string()
+                .ends_with("/domains/example.com/dns returned 409: This DNS entry already exists")
+        );
+        mock.assert_calls(1);
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn delete_with_body() {
+        let server = httpmock::MockServer::start();
+        let body = serde_json::json!({
+            "dnsEntry": {"name": "www", "expire": 300, "type": "A", "content": "192.0.2.1"}
+        });
+        let mock = server.mock(|when, then| {
+            when.method(DELETE)
+                .path("/domains/example.com/dns")
+                .header("Content-Type", "application/json")
+                .json_body(body);
+            then.status(204);
+        });
+
+        let client = Client::test(server.base_url());
+        let entry = "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap();
+        client.dns_entry_delete("example.com", entry).unwrap();
+        mock.assert_calls(1);
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn delete_all_with_body() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/domains/example.com/dns");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .body(
+                    r#"{"dnsEntries":[
+                        {"name":"_acme-challenge","expire":60,"type":"TXT","content":"first"},
+                        {"name":"www","expire":300,"type":"A","content":"192.0.2.1"},
+                        {"name":"_acme-challenge","expire":60,"type":"TXT","content":"second"}
+                    ]}"#,
+                );
+        });
+        let mocks = ["first", "second"].map(|content| {
+            let body = serde_json::json!({
+                "dnsEntry": {"name": "_acme-challenge", "expire": 60, "type": "TXT", "content": content}
+            });
+            server.mock(|when, then| {
+                when.method(DELETE)
+                    .path("/domains/example.com/dns")
+                    .json_body(body);
+                then.status(204);
+            })
+        });
+
+        let client = Client::test(server.base_url());
+        client
+            .dns_entry_delete_all("example.com", DnsEntry::is_acme_challenge)
+            .unwrap();
+        for mock in mocks {
+            mock.assert_calls(1);
+        }
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn retry_server_error() {
+        let server = httpmock::MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/api-test");
+            then.status(503);
+        });
+
+        let client = Client::test(server.base_url()).with_retry_policy(fast_retries());
+        let error = client.api_test().unwrap_err();
+        assert_eq!(error.status(), Some(503));
+        mock.assert_calls(3);
+    }
+
+    #[test]
+    fn no_retry_mutation_server_error() {
+        let transport = MemoryTransport::default().respond(
+            Method::Post,
+            "/domains/example.com/dns",
+            Response::new(503),
+        );
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone())
+            .with_retry_policy(fast_retries());
+        let entry = "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap();
+        let error = client.dns_entry_insert("example.com", entry).unwrap_err();
+        assert_eq!(error.status(), Some(503));
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn no_retry_client_error() {
+        let server = httpmock::MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/api-test");
+            then.status(400);
+        });
+
+        let client = Client::test(server.base_url()).with_retry_policy(fast_retries());
+        assert!(client.api_test().is_err());
+        mock.assert_calls(1);
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn retry_rate_limited() {
+        let server = httpmock::MockServer::start();
+        let reset = chrono::Utc::now().timestamp().to_string();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/api-test");
+            then.status(429)
+                .header("X-Rate-Limit-Remaining", "0")
+                .header("X-Rate-Limit-Reset", reset.as_str());
+        });
+
+        let client = Client::test(server.base_url()).with_retry_policy(fast_retries());
+        assert!(client.api_test().unwrap_err().is_rate_limited());
+        assert_eq!(client.rate_limit().unwrap().remaining, 0);
+        mock.assert_calls(3);
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn rate_limit_remaining() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/api-test");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .header("X-Rate-Limit-Limit", "1000")
+                .header("X-Rate-Limit-Remaining", "997")
+                .body(r#"{"ping":"pong"}"#);
+        });
+
+        let client = Client::test(server.base_url());
+        assert!(client.rate_limit().is_none());
+        client.api_test().unwrap();
+        let rate_limit = client.rate_limit().unwrap();
+        assert_eq!(rate_limit.limit, Some(1000));
+        assert_eq!(rate_limit.remaining, 997);
+    }
+
+    #[test]
+    fn memory_transport() {
+        let transport = MemoryTransport::default()
+            .respond(
+                Method::Get,
+                "/api-test",
+                Response::json(200, r#"{"ping":"pong"}"#),
+            )
+            .respond(Method::Patch, "/vps/transipdemo-vps", Response::new(204));
+
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone());
+        assert_eq!(client.api_test().unwrap(), "pong");
+        client.vps_start("transipdemo-vps").unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].url, "memory://transip/vps/transipdemo-vps");
+        assert_eq!(
+            requests[1].body.as_deref(),
+            Some(&br#"{"action":"start"}"#[..])
+        );
+        assert!(
+            requests[1]
+                .headers
+                .iter()
+                .any(|(name, value)| name == "Authorization" && value.starts_with("Bearer "))
+        );
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        fn assert_shareable<T: Clone + Send + Sync>() {}
+        assert_shareable::<Client>();
+
+        let token = Token::test_raw(chrono::Utc::now().timestamp() + 300);
+        let transport = MemoryTransport::default()
+            .respond(
+                Method::Post,
+                "/auth",
+                Response::json(201, format!(r#"{{"token":"{token}"}}"#)),
+            )
+            .respond(
+                Method::Get,
+                "/api-test",
+                Response::json(200, r#"{"ping":"pong"}"#),
+            );
+        let client = Client::builder()
+            .user_name("example")
+            .private_key_pem(include_str!("../fixtures/test-key.pem"))
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                let client = client.clone();
+                scope.spawn(move || assert_eq!(client.api_test().unwrap(), "pong"));
+            }
+        });
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 9);
+        assert_eq!(
+            requests
+                .iter()
+                .filter(|request| request.method == Method::Post)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn token_info() {
+        let client = Client::fixture("general");
+        let info = client.token_info().unwrap();
+        assert_eq!(info.customer_id, "60449");
+        assert!(info.whitelisted_only());
+        assert!(!info.read_only);
+    }
+
+    fn auth_client(auth: Response, store: crate::MemoryTokenStore) -> (Client, MemoryTransport) {
+        let transport = MemoryTransport::default()
+            .respond(Method::Post, "/auth", auth)
+            .respond(
+                Method::Get,
+                "/api-test",
+                Response::json(200, r#"{"ping":"pong"}"#),
+            );
+        let client = Client::builder()
+            .user_name("example")
+            .private_key_pem(include_str!("../fixtures/test-key.pem"))
+            .transport(transport.clone())
+            .token_store(store)
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        (client, transport)
+    }
+
+    fn token_response(exp: i64) -> Response {
+        let token = Token::test_raw(exp);
+        Response::json(201, format!(r#"{{"token":"{token}"}}"#))
+    }
+
+    #[test]
+    fn refresh_error() {
+        let auth = Response::json(401, r#"{"error":"Signature invalid"}"#);
+        let (client, _) = auth_client(auth, Default::default());
+        let error = client.api_test().unwrap_err();
+        assert_eq!(error.status(), Some(401));
+    }
+
+    #[test]
+    fn refresh_within_margin() {
+        use crate::TokenStore;
+
+        let now = chrono::Utc::now().timestamp();
+        let store = crate::MemoryTokenStore::new();
+        let expiring = Token::test_raw(now + 20);
+        store.save(&expiring).unwrap();
+
+        let (client, transport) = auth_client(token_response(now + 3600), store.clone());
+        let client = client.with_refresh_margin(Duration::from_secs(10));
+        client.api_test().unwrap();
+        assert_eq!(transport.requests().len(), 1);
+
+        let client = client.with_refresh_margin(Duration::from_secs(60));
+        client.api_test().unwrap();
+        assert_eq!(transport.requests().len(), 3);
+        assert_ne!(store.token(), Some(expiring.clone()));
+
+        // a failing renewal keeps using the token while it is valid
+        store.save(&expiring).unwrap();
+        let auth = Response::json(500, r#"{"error":"Internal error"}"#);
+        let (client, transport) = auth_client(auth, store);
+        client.api_test().unwrap();
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[test]
+    fn dry_run() {
+        let transport = MemoryTransport::default();
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone())
+            .with_dry_run(true);
+        let entry = "www 300 A 192.0.2.1".parse::<DnsEntry>().unwrap();
+        client.dns_entry_insert("example.com", entry).unwrap();
+        client.vps_stop("example-vps").unwrap();
+
+        assert!(transport.requests().is_empty());
+        let planned_changes = client.clone().planned_changes();
+        assert_eq!(planned_changes.len(), 2);
+        assert_eq!(planned_changes[0].method, Method::Post);
+        assert_eq!(
+            planned_changes[0].url,
+            "memory://transip/domains/example.com/dns"
+        );
+        assert_eq!(
+            planned_changes[0].body.as_ref().unwrap()["dnsEntry"]["name"],
+            "www"
+        );
+        assert_eq!(planned_changes[1].method, Method::Patch);
+    }
+
+    #[test]
+    fn strict_read_only() {
+        let transport = MemoryTransport::default().respond(
+            Method::Get,
+            "/api-test",
+            Response::json(200, r#"{"ping":"pong"}"#),
+        );
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone())
+            .with_strict_read_only(true);
+        assert_eq!(client.api_test().unwrap(), "pong");
+        let error = client.vps_stop("example-vps").unwrap_err();
+        assert!(error.is_read_only());
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_mode() {
+        let transport = MemoryTransport::default()
+            .respond(Method::Patch, "/vps/example-vps?test=1", Response::new(204))
+            .respond(
+                Method::Get,
+                "/domains",
+                Response::json(200, r#"{"domains":[]}"#),
+            );
+        let client = Client::test_with_transport("memory://transip".into(), transport.clone())
+            .with_test_mode(true);
+        client.vps_stop("example-vps").unwrap();
+        assert!(client.domain_list().unwrap().is_empty());
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].url, "memory://transip/vps/example-vps?test=1");
+        assert!(requests[1].url.ends_with("&test=1"));
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt::Display;
+
+use serde_json::Value;
+
+use crate::transport::{Method, Request};
+
+/// Request that would modify something, recorded instead of sent in dry-run mode.
+/// See [`Client::with_dry_run`](crate::Client::with_dry_run).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlannedChange {
+    pub method: Method,
+    pub url: String,
+    /// Json body of the request
+    pub body: Option<Value>,
+}
+
+impl From<&Request> for PlannedChange {
+    fn from(request: &Request) -> Self {
+        Self {
+            method: request.method,
+            url: request.url.clone(),
+            body: request
+                .body
+                .as_deref()
+                .and_then(|body| serde_json::from_slice(body).ok()),
+        }
+    }
+}
+
+impl Display for PlannedChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.method, self.url)?;
+        if let Some(body) = &self.body {
+            write!(f, " {body}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PlannedChange;
+    use crate::transport::{Method, Request};
+
+    #[test]
+    fn display() {
+        let request = Request::new(Method::Patch, "https://api.transip.nl/v6/vps/example")
+            .json(&serde_json::json!({"action": "stop"}))
+            .unwrap();
+        assert_eq!(
+            PlannedChange::from(&request).to_string(),
+            r#"PATCH https://api.transip.nl/v6/vps/example {"action":"stop"}"#
+        );
+        let request = Request::new(Method::Delete, "https://api.transip.nl/v6/vps/example");
+        assert_eq!(
+            PlannedChange::from(&request).to_string(),
+            "DELETE https://api.transip.nl/v6/vps/example"
+        );
+    }
+}
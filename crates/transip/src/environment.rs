@@ -2,13 +2,14 @@ use std::{path::Path, str::FromStr};
 
 use crate::{Configuration, Error, Result, authentication::TokenExpiration, error::ResultExt};
 
-const TRANSIP_API_PRIVATE_KEY: &str = "TRANSIP_API_PRIVATE_KEY";
-const TRANSIP_API_USERNAME: &str = "TRANSIP_API_USERNAME";
-const TRANSIP_API_TOKEN_PATH: &str = "TRANSIP_API_TOKEN_PATH";
-const TRANSIP_API_WHITELISTED_ONLY: &str = "TRANSIP_API_WHITELISTED_ONLY";
-const TRANSIP_API_READONLY: &str = "TRANSIP_API_READONLY";
-const TRANSIP_API_IPV6ONLY: &str = "TRANSIP_API_IPV6ONLY";
-const TRANSIP_API_TOKEN_EXPIRATION: &str = "TRANSIP_API_TOKEN_EXPIRATION";
+pub(crate) const TRANSIP_API_PRIVATE_KEY: &str = "TRANSIP_API_PRIVATE_KEY";
+pub(crate) const TRANSIP_API_USERNAME: &str = "TRANSIP_API_USERNAME";
+pub(crate) const TRANSIP_API_TOKEN_PATH: &str = "TRANSIP_API_TOKEN_PATH";
+pub(crate) const TRANSIP_API_WHITELISTED_ONLY: &str = "TRANSIP_API_WHITELISTED_ONLY";
+pub(crate) const TRANSIP_API_READONLY: &str = "TRANSIP_API_READONLY";
+pub(crate) const TRANSIP_API_IPV6ONLY: &str = "TRANSIP_API_IPV6ONLY";
+pub(crate) const TRANSIP_API_TOKEN_EXPIRATION: &str = "TRANSIP_API_TOKEN_EXPIRATION";
+pub(crate) const TRANSIP_API_PRIVATE_KEY_PASSPHRASE: &str = "TRANSIP_API_PRIVATE_KEY_PASSPHRASE";
 
 const ENVIRONMENT_VARIABLES: [&str; 7] = [
     TRANSIP_API_USERNAME,
@@ -23,6 +24,7 @@ const ENVIRONMENT_VARIABLES: [&str; 7] = [
 struct Environment {
     user_name: String,
     private_key: String,
+    private_key_passphrase: Option<String>,
     token_path: String,
     whitelisted_only: bool,
     read_only: bool,
@@ -39,6 +41,10 @@ impl Configuration for Environment {
         self.private_key.as_str()
     }
 
+    fn private_key_passphrase(&self) -> Option<&str> {
+        self.private_key_passphrase.as_deref()
+    }
+
     fn token_path(&self) -> &str {
         self.token_path.as_str()
     }
@@ -99,6 +105,7 @@ pub fn configuration_from_environment() -> Result<Box<dyn Configuration>> {
     Ok(Box::new(Environment {
         user_name: var(TRANSIP_API_USERNAME)?,
         private_key: var(TRANSIP_API_PRIVATE_KEY)?,
+        private_key_passphrase: var(TRANSIP_API_PRIVATE_KEY_PASSPHRASE).ok(),
         token_path: var(TRANSIP_API_TOKEN_PATH)?,
         whitelisted_only: var(TRANSIP_API_WHITELISTED_ONLY).and_then(parse::<bool>)?,
         read_only: var(TRANSIP_API_READONLY).and_then(parse::<bool>)?,
@@ -120,6 +127,7 @@ pub fn demo_configuration() -> Box<dyn Configuration> {
     Box::new(Environment {
         user_name: Default::default(),
         private_key: Default::default(),
+        private_key_passphrase: None,
         token_path: Default::default(),
         read_only: false,
         ipv6_only: false,
@@ -1,13 +1,33 @@
 use std::{env::VarError, str::ParseBoolError};
 
+use serde::Deserialize;
 use thiserror::Error;
 
+const NOT_FOUND: u16 = 404;
+const CONFLICT: u16 = 409;
+const TOO_MANY_REQUESTS: u16 = 429;
+
 /// All failable functions in this crate should use this Error
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Key: {0}")]
     Key(&'static str),
 
+    /// Invalid settings passed to the [`ClientBuilder`](crate::ClientBuilder)
+    #[error("Configuration: {0}")]
+    Configuration(String),
+
+    /// A mutation was rejected by the client, because strict read-only mode is enabled
+    #[error("Read only: {method} {url} not allowed")]
+    ReadOnly { method: String, url: String },
+
+    /// Replacing the dns entries failed, and restoring the saved entries failed too
+    #[error("Rollback: {rollback}, after {error}")]
+    Rollback {
+        error: Box<Error>,
+        rollback: Box<Error>,
+    },
+
     #[error("Rejected: {0}")]
     Rejected(String),
 
@@ -22,17 +42,39 @@ pub enum Error {
 
     //    #[error("Json: {0}")]
     //    Json(#[from] ureq::serde_json::Error),
+    #[cfg(feature = "ureq")]
     #[error("Ureq: {0}")]
     Ureq(#[from] ureq::Error),
 
+    /// The api responded with a 4xx or 5xx status code
+    #[error("Api: {method} {url} returned {status}: {message}")]
+    Api {
+        status: u16,
+        message: String,
+        method: String,
+        url: String,
+    },
+
+    #[cfg(feature = "async")]
+    #[error("Reqwest: {0}")]
+    Reqwest(#[from] reqwest::Error),
+
     // #[allow(dead_code)]
     #[error("IP 4 address missing")]
     Ipv4,
 
-    #[allow(dead_code)]
+    /// An acme challenge was not visible at every nameserver before the timeout
     #[error("Acme challenge not found")]
     AcmeChallege,
 
+    /// The name of an acme challenge is not part of the domain, retrying will not help
+    #[error("Acme identifier: {0}")]
+    AcmeIdentifier(String),
+
+    /// A nameserver could not be asked for an acme challenge
+    #[error("Dns: {0}")]
+    Dns(String),
+
     #[error("Address: {0}")]
     Address(#[from] std::net::AddrParseError),
 
@@ -54,6 +96,10 @@ pub enum Error {
     #[error("Parse Dns entry: {0}")]
     ParseDnsEntry(&'static str),
 
+    /// A zone file could not be read, the message starts with the line number
+    #[error("Parse zone: {0}")]
+    ParseZone(String),
+
     #[error("Parse Mailbox entry: {0}")]
     ParseMailboxEntry(String),
 
@@ -79,10 +125,79 @@ pub enum Error {
     Strum(#[from] strum::ParseError),
 
     #[error("Serialization: {0}")]
-    Serialization(Box<dyn std::error::Error>),
+    Serialization(Box<dyn std::error::Error + Send + Sync>),
 
     #[error("Json: {0}")]
     Json(#[from] serde_json::Error),
+
+    /// A [`TokenStore`](crate::TokenStore) failed to load or save the token
+    #[error("Token store: {0}")]
+    TokenStore(String),
+
+    #[error("Replay: {0}")]
+    Replay(String),
+}
+
+/// Body of an error response, like `{"error": "Dns entry not found"}`
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+impl Error {
+    /// Create an [`Error::Api`] from the status and body of an error response.
+    /// If the body does not contain the error json, the raw body is used as message.
+    pub(crate) fn api<M, U>(method: M, url: U, status: u16, body: &[u8]) -> Self
+    where
+        M: Into<String>,
+        U: Into<String>,
+    {
+        let message = serde_json::from_slice::<ErrorResponse>(body)
+            .map(|response| response.error)
+            .unwrap_or_else(|_| String::from_utf8_lossy(body).trim().to_owned());
+        Self::Api {
+            status,
+            message,
+            method: method.into(),
+            url: url.into(),
+        }
+    }
+
+    /// The http status code if the api responded with an error
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// The error message returned by the api
+    pub fn api_message(&self) -> Option<&str> {
+        match self {
+            Self::Api { message, .. } => Some(message.as_str()),
+            _ => None,
+        }
+    }
+
+    /// The api responded with 404 Not Found, for example when a domain does not exist
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(NOT_FOUND)
+    }
+
+    /// The client refused to send a mutation in strict read-only mode
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, Self::ReadOnly { .. })
+    }
+
+    /// The api responded with 409 Conflict, for example when a dns entry already exists
+    pub fn is_conflict(&self) -> bool {
+        self.status() == Some(CONFLICT)
+    }
+
+    /// The api responded with 429 Too Many Requests
+    pub fn is_rate_limited(&self) -> bool {
+        self.status() == Some(TOO_MANY_REQUESTS)
+    }
 }
 
 pub(crate) trait ResultExt<T, E>
@@ -100,3 +215,47 @@ where
         self.map_err(Into::into)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Error;
+
+    #[test]
+    fn api_error_from_json() {
+        let error = Error::api(
+            "GET",
+            "https://api.transip.nl/v6/domains/example.com",
+            404,
+            br#"{"error":"Domain with name 'example.com' not found"}"#,
+        );
+        assert!(error.is_not_found());
+        assert!(!error.is_conflict());
+        assert_eq!(
+            error.api_message(),
+            Some("Domain with name 'example.com' not found")
+        );
+        assert_eq!(
+            error.to_string(),
+            "Api: GET https://api.transip.nl/v6/domains/example.com returned 404: Domain with name 'example.com' not found"
+        );
+    }
+
+    #[test]
+    fn api_error_without_json() {
+        let error = Error::api(
+            "POST",
+            "https://api.transip.nl/v6/auth",
+            429,
+            b"Slow down\n",
+        );
+        assert!(error.is_rate_limited());
+        assert_eq!(error.api_message(), Some("Slow down"));
+    }
+
+    #[test]
+    fn no_api_error() {
+        let error = Error::Token;
+        assert_eq!(error.status(), None);
+        assert!(!error.is_not_found());
+    }
+}
@@ -6,7 +6,6 @@ use std::{
 
 pub trait FileSystem {
     fn reader(&self) -> Result<File>;
-    fn writer(&self) -> Result<File>;
 }
 
 impl<P> FileSystem for P
@@ -16,12 +15,4 @@ where
     fn reader(&self) -> Result<File> {
         OpenOptions::new().read(true).open(self).err_into()
     }
-
-    fn writer(&self) -> Result<File> {
-        OpenOptions::new()
-            .create_new(true)
-            .write(true)
-            .open(self)
-            .err_into()
-    }
 }
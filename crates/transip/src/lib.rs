@@ -1,24 +1,54 @@
-#![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"))]
+// The examples in the readme need the default ureq transport
+#![cfg_attr(
+    feature = "ureq",
+    doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"))
+)]
 
+#[cfg(feature = "async")]
+pub use crate::async_client::AsyncClient;
+pub use crate::builder::{ClientBuilder, IpFamily};
 pub use crate::client::Client;
+pub use crate::dry_run::PlannedChange;
 pub use crate::environment::configuration_from_environment;
-use authentication::TokenExpiration;
+pub use crate::profile::{Profile, config_file_path, configuration_from_profile};
+pub use crate::renewal::TokenRenewal;
+pub use crate::retry::{RateLimit, RetryPolicy};
+#[cfg(feature = "keyring")]
+pub use authentication::KeyringTokenStore;
+pub use authentication::{
+    FileTokenStore, MemoryTokenStore, TokenExpiration, TokenInfo, TokenStore,
+};
 pub use error::Error;
 
+/// DNS-01 challenges for acme certificates, like those of Let's Encrypt
+pub mod acme;
 /// See [api specification](https://api.transip.nl/rest/docs.html#header-api-specification)
 pub mod api;
+#[cfg(feature = "async")]
+mod async_client;
 mod authentication;
 mod base64;
+mod builder;
 mod client;
+mod dry_run;
 mod environment;
 mod error;
 mod fs;
+mod profile;
+mod renewal;
+mod retry;
+pub mod transport;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-pub trait Configuration {
+pub trait Configuration: Send + Sync {
     fn user_name(&self) -> &str;
+    /// Name of the file with the private key, or the PEM encoded private key itself
     fn private_key_pem_file(&self) -> &str;
+    /// Passphrase of an encrypted private key
+    fn private_key_passphrase(&self) -> Option<&str> {
+        None
+    }
     fn token_path(&self) -> &str;
     fn whitelisted_only(&self) -> bool;
     fn read_only(&self) -> bool;
@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::authentication::TokenExpiration;
+use crate::environment::{
+    TRANSIP_API_IPV6ONLY, TRANSIP_API_PRIVATE_KEY, TRANSIP_API_PRIVATE_KEY_PASSPHRASE,
+    TRANSIP_API_READONLY, TRANSIP_API_TOKEN_EXPIRATION, TRANSIP_API_TOKEN_PATH,
+    TRANSIP_API_USERNAME, TRANSIP_API_WHITELISTED_ONLY,
+};
+use crate::{Configuration, Error, Result};
+
+/// Overrides the location of the configuration file
+const TRANSIP_API_CONFIG: &str = "TRANSIP_API_CONFIG";
+/// Selects the profile when no name is given
+const TRANSIP_API_PROFILE: &str = "TRANSIP_API_PROFILE";
+
+const DEFAULT_PROFILE: &str = "default";
+
+/// Contents of the configuration file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// Named set of settings from the configuration file.
+///
+/// ```toml
+/// default_profile = "production"
+///
+/// [profiles.production]
+/// user_name = "paulusminus"
+/// private_key = "/etc/transip/production.pem"
+/// token_path = "/var/cache/transip/production.token"
+/// read_only = false
+/// whitelisted_only = false
+/// ipv6_only = false
+/// token_expiration = "1 hour"
+/// ```
+///
+/// The private key can also be the PEM encoded key itself.
+/// Relative paths are resolved from the directory of the configuration file.
+/// Settings that are left out fall back to the environment variables,
+/// or to the defaults of the [`ClientBuilder`](crate::ClientBuilder).
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub user_name: Option<String>,
+    pub private_key: Option<PathBuf>,
+    /// Passphrase of an encrypted private key.
+    /// Prefer the `TRANSIP_API_PRIVATE_KEY_PASSPHRASE` environment variable over storing it in the file.
+    pub private_key_passphrase: Option<String>,
+    pub token_path: Option<PathBuf>,
+    pub read_only: Option<bool>,
+    pub whitelisted_only: Option<bool>,
+    pub ipv6_only: Option<bool>,
+    pub token_expiration: Option<String>,
+}
+
+fn invalid<S: Into<String>>(message: S) -> Error {
+    Error::Configuration(message.into())
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Location of the configuration file.
+/// `TRANSIP_API_CONFIG` has precedence over `$XDG_CONFIG_HOME/transip/config.toml`
+/// and `$HOME/.config/transip/config.toml`.
+pub fn config_file_path() -> Option<PathBuf> {
+    config_file_path_from(env)
+}
+
+fn config_file_path_from<F>(lookup: F) -> Option<PathBuf>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let non_empty = |name| lookup(name).filter(|value: &String| !value.is_empty());
+    non_empty(TRANSIP_API_CONFIG)
+        .map(PathBuf::from)
+        .or_else(|| {
+            non_empty("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| non_empty("HOME").map(|home| Path::new(&home).join(".config")))
+                .map(|directory| directory.join("transip").join("config.toml"))
+        })
+}
+
+impl Profile {
+    /// Load a profile from the configuration file, with the environment variables applied.
+    ///
+    /// Without a name the profile in `TRANSIP_API_PROFILE` is used,
+    /// then the `default_profile` of the file and finally the profile named `default`.
+    /// If no configuration file exists only the environment variables are used.
+    pub fn load(name: Option<&str>) -> Result<Self> {
+        let profile_name = name.map(str::to_owned).or_else(|| env(TRANSIP_API_PROFILE));
+        let profile = match config_file_path().filter(|path| path.is_file()) {
+            Some(path) => Self::from_file(path, profile_name.as_deref())?,
+            None => match profile_name {
+                Some(name) => {
+                    return Err(invalid(format!(
+                        "profile {name} not found, there is no configuration file"
+                    )));
+                }
+                None => Self::default(),
+            },
+        };
+        profile.with_environment(env)
+    }
+
+    /// Read a profile from a configuration file, without looking at the environment
+    pub fn from_file<P: AsRef<Path>>(path: P, name: Option<&str>) -> Result<Self> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)?;
+        let profile = Self::parse(&s, name)
+            .map_err(|error| invalid(format!("{}: {error}", path.display())))?;
+        Ok(profile.resolve_paths(path.parent().unwrap_or(Path::new(""))))
+    }
+
+    fn parse(s: &str, name: Option<&str>) -> std::result::Result<Self, String> {
+        let mut file = toml::from_str::<ConfigFile>(s).map_err(|error| error.to_string())?;
+        let name = name
+            .or(file.default_profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE)
+            .to_owned();
+        file.profiles
+            .remove(&name)
+            .ok_or_else(|| format!("profile {name} not found"))
+    }
+
+    fn resolve_paths(mut self, directory: &Path) -> Self {
+        let resolve = |path: PathBuf| {
+            if path.is_relative() && !path.to_string_lossy().starts_with("-----BEGIN") {
+                directory.join(path)
+            } else {
+                path
+            }
+        };
+        self.private_key = self.private_key.map(resolve);
+        self.token_path = self.token_path.map(resolve);
+        self
+    }
+
+    /// Settings from the environment variables take precedence over the file
+    fn with_environment<F>(mut self, lookup: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let boolean = |name: &str| {
+            lookup(name)
+                .map(|value| {
+                    value
+                        .parse::<bool>()
+                        .map_err(|_| invalid(format!("{name} should contain true or false")))
+                })
+                .transpose()
+        };
+        if let Some(user_name) = lookup(TRANSIP_API_USERNAME) {
+            self.user_name = Some(user_name);
+        }
+        if let Some(private_key) = lookup(TRANSIP_API_PRIVATE_KEY) {
+            self.private_key = Some(private_key.into());
+        }
+        if let Some(passphrase) = lookup(TRANSIP_API_PRIVATE_KEY_PASSPHRASE) {
+            self.private_key_passphrase = Some(passphrase);
+        }
+        if let Some(token_path) = lookup(TRANSIP_API_TOKEN_PATH) {
+            self.token_path = Some(token_path.into());
+        }
+        if let Some(token_expiration) = lookup(TRANSIP_API_TOKEN_EXPIRATION) {
+            self.token_expiration = Some(token_expiration);
+        }
+        self.read_only = boolean(TRANSIP_API_READONLY)?.or(self.read_only);
+        self.whitelisted_only = boolean(TRANSIP_API_WHITELISTED_ONLY)?.or(self.whitelisted_only);
+        self.ipv6_only = boolean(TRANSIP_API_IPV6ONLY)?.or(self.ipv6_only);
+        Ok(self)
+    }
+
+    pub(crate) fn expiration(&self) -> Result<Option<TokenExpiration>> {
+        self.token_expiration
+            .as_deref()
+            .map(str::parse::<TokenExpiration>)
+            .transpose()
+    }
+}
+
+/// Settings of a profile that has all required fields
+struct ProfileConfiguration {
+    user_name: String,
+    private_key: String,
+    private_key_passphrase: Option<String>,
+    token_path: String,
+    whitelisted_only: bool,
+    read_only: bool,
+    ipv6_only: bool,
+    token_expiration: TokenExpiration,
+}
+
+impl Configuration for ProfileConfiguration {
+    fn user_name(&self) -> &str {
+        self.user_name.as_str()
+    }
+
+    fn private_key_pem_file(&self) -> &str {
+        self.private_key.as_str()
+    }
+
+    fn private_key_passphrase(&self) -> Option<&str> {
+        self.private_key_passphrase.as_deref()
+    }
+
+    fn token_path(&self) -> &str {
+        self.token_path.as_str()
+    }
+
+    fn whitelisted_only(&self) -> bool {
+        self.whitelisted_only
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn ipv6_only(&self) -> bool {
+        self.ipv6_only
+    }
+
+    fn token_expiration(&self) -> TokenExpiration {
+        self.token_expiration.clone()
+    }
+}
+
+impl TryFrom<Profile> for Box<dyn Configuration> {
+    type Error = Error;
+    fn try_from(profile: Profile) -> Result<Self> {
+        let token_expiration = profile.expiration()?.unwrap_or_default();
+        let path = |path: Option<PathBuf>| {
+            path.map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        Ok(Box::new(ProfileConfiguration {
+            user_name: profile
+                .user_name
+                .ok_or_else(|| invalid("user name is missing"))?,
+            private_key: profile
+                .private_key
+                .map(|path| path.to_string_lossy().into_owned())
+                .ok_or_else(|| invalid("private key is missing"))?,
+            private_key_passphrase: profile.private_key_passphrase,
+            token_path: path(profile.token_path),
+            whitelisted_only: profile.whitelisted_only.unwrap_or_default(),
+            read_only: profile.read_only.unwrap_or_default(),
+            ipv6_only: profile.ipv6_only.unwrap_or_default(),
+            token_expiration,
+        }))
+    }
+}
+
+/// get configuration from a profile in the configuration file,
+/// with the environment variables taking precedence
+pub fn configuration_from_profile(name: Option<&str>) -> Result<Box<dyn Configuration>> {
+    Profile::load(name).and_then(TryInto::try_into)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    use super::{Profile, config_file_path_from};
+    use crate::{Configuration, TokenExpiration};
+
+    const CONFIG: &str = r#"
+default_profile = "staging"
+
+[profiles.production]
+user_name = "production"
+private_key = "/etc/transip/production.pem"
+token_path = "production.token"
+read_only = false
+
+[profiles.staging]
+user_name = "staging"
+private_key = "keys/staging.pem"
+read_only = true
+token_expiration = "5 minutes"
+"#;
+
+    fn lookup(variables: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let variables = variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        move |name| variables.get(name).cloned()
+    }
+
+    #[test]
+    fn select_profile() {
+        let production = Profile::parse(CONFIG, Some("production")).unwrap();
+        assert_eq!(production.user_name.as_deref(), Some("production"));
+        assert_eq!(production.read_only, Some(false));
+        assert_eq!(production.ipv6_only, None);
+
+        let staging = Profile::parse(CONFIG, None).unwrap();
+        assert_eq!(staging.user_name.as_deref(), Some("staging"));
+        assert_eq!(
+            staging.expiration().unwrap(),
+            Some(TokenExpiration::Minutes(5))
+        );
+
+        let error = Profile::parse(CONFIG, Some("customer")).unwrap_err();
+        assert_eq!(error, "profile customer not found");
+        assert!(Profile::parse("[profiles.default]\nusername = \"x\"", None).is_err());
+    }
+
+    #[test]
+    fn relative_paths() {
+        let profile = Profile::parse(CONFIG, None)
+            .unwrap()
+            .resolve_paths(Path::new("/etc/transip"));
+        assert_eq!(
+            profile.private_key,
+            Some(PathBuf::from("/etc/transip/keys/staging.pem"))
+        );
+        let profile = Profile::parse(CONFIG, Some("production"))
+            .unwrap()
+            .resolve_paths(Path::new("/etc/transip"));
+        assert_eq!(
+            profile.private_key,
+            Some(PathBuf::from("/etc/transip/production.pem"))
+        );
+        assert_eq!(
+            profile.token_path,
+            Some(PathBuf::from("/etc/transip/production.token"))
+        );
+    }
+
+    #[test]
+    fn environment_precedence() {
+        let profile = Profile::parse(CONFIG, None)
+            .unwrap()
+            .with_environment(lookup(&[
+                ("TRANSIP_API_USERNAME", "override"),
+                ("TRANSIP_API_READONLY", "false"),
+                ("TRANSIP_API_IPV6ONLY", "true"),
+            ]))
+            .unwrap();
+        assert_eq!(profile.user_name.as_deref(), Some("override"));
+        assert_eq!(profile.read_only, Some(false));
+        assert_eq!(profile.ipv6_only, Some(true));
+        assert_eq!(profile.private_key, Some(PathBuf::from("keys/staging.pem")));
+
+        let error = Profile::default()
+            .with_environment(lookup(&[("TRANSIP_API_READONLY", "yes")]))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Configuration: TRANSIP_API_READONLY should contain true or false"
+        );
+    }
+
+    #[test]
+    fn configuration() {
+        let profile = Profile::parse(CONFIG, None).unwrap();
+        let configuration = Box::<dyn Configuration>::try_from(profile).unwrap();
+        assert_eq!(configuration.user_name(), "staging");
+        assert_eq!(configuration.token_path(), "");
+        assert!(configuration.read_only());
+        assert!(!configuration.whitelisted_only());
+
+        let missing = Profile {
+            user_name: Some("staging".to_owned()),
+            ..Default::default()
+        };
+        assert!(Box::<dyn Configuration>::try_from(missing).is_err());
+    }
+
+    #[test]
+    fn file_location() {
+        assert_eq!(
+            config_file_path_from(lookup(&[
+                ("TRANSIP_API_CONFIG", "/etc/transip.toml"),
+                ("HOME", "/home/user")
+            ])),
+            Some(PathBuf::from("/etc/transip.toml"))
+        );
+        assert_eq!(
+            config_file_path_from(lookup(&[
+                ("XDG_CONFIG_HOME", "/home/user/.xdg"),
+                ("HOME", "/home/user")
+            ])),
+            Some(PathBuf::from("/home/user/.xdg/transip/config.toml"))
+        );
+        assert_eq!(
+            config_file_path_from(lookup(&[("HOME", "/home/user")])),
+            Some(PathBuf::from("/home/user/.config/transip/config.toml"))
+        );
+        assert_eq!(config_file_path_from(lookup(&[])), None);
+    }
+}
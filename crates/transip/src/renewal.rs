@@ -0,0 +1,92 @@
+use core::time::Duration;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+
+use crate::Client;
+
+/// Minimum time between two renewal attempts
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Renews the token of a [`Client`] in a background thread,
+/// so long-lived processes never wait for a token request.
+///
+/// Created by [`Client::renew_token_in_background`].
+/// The thread stops when the handle is dropped.
+pub struct TokenRenewal {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Client {
+    /// Start renewing the token before it expires within the refresh margin.
+    /// Clones of the client share the renewed token.
+    pub fn renew_token_in_background(&self) -> TokenRenewal {
+        let client = self.clone();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = std::thread::spawn(move || {
+            let mut wait = client.token_refresh_in();
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(wait) {
+                wait = match client.token() {
+                    Ok(_) => client.token_refresh_in().max(RETRY_INTERVAL),
+                    Err(error) => {
+                        tracing::error!("Token renewal failed with {}", error);
+                        RETRY_INTERVAL
+                    }
+                };
+            }
+        });
+        TokenRenewal {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for TokenRenewal {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take()
+            && thread.join().is_err()
+        {
+            tracing::error!("Token renewal thread panicked");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    use crate::authentication::Token;
+    use crate::transport::{MemoryTransport, Method, Response};
+    use crate::{Client, MemoryTokenStore};
+
+    #[test]
+    fn renew_in_background() {
+        let token = Token::test_raw(chrono::Utc::now().timestamp() + 3600);
+        let transport = MemoryTransport::default().respond(
+            Method::Post,
+            "/auth",
+            Response::json(201, format!(r#"{{"token":"{token}"}}"#)),
+        );
+        let store = MemoryTokenStore::new();
+        let client = Client::builder()
+            .user_name("example")
+            .private_key_pem(include_str!("../fixtures/test-key.pem"))
+            .transport(transport.clone())
+            .token_store(store.clone())
+            .build()
+            .unwrap();
+
+        let renewal = client.renew_token_in_background();
+        for _ in 0..100 {
+            if store.token().is_some() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        drop(renewal);
+        assert_eq!(store.token(), Some(token));
+        assert_eq!(transport.requests().len(), 1);
+    }
+}
@@ -0,0 +1,289 @@
+use core::time::Duration;
+
+use chrono::{DateTime, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::Error;
+use crate::transport::{Method, Response};
+
+const X_RATE_LIMIT_LIMIT: &str = "X-Rate-Limit-Limit";
+const X_RATE_LIMIT_REMAINING: &str = "X-Rate-Limit-Remaining";
+const X_RATE_LIMIT_RESET: &str = "X-Rate-Limit-Reset";
+
+/// Controls if and how failed requests are retried.
+///
+/// Requests are retried when the api responds with `429 Too Many Requests`,
+/// or when the connection to the api could not be made.
+/// GET requests are also retried after a `5xx` status code, a timeout or another io error.
+/// Requests that modify something may have been carried out in those cases,
+/// so they are only retried then if [`retry_mutations`](RetryPolicy::retry_mutations) is set.
+/// Between attempts the client sleeps using exponential backoff with jitter.
+/// After a `429` response the client waits until the rate limit window resets.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use transip::RetryPolicy;
+///
+/// let policy = RetryPolicy {
+///     max_attempts: 5,
+///     initial_backoff: Duration::from_secs(1),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on every following retry
+    pub initial_backoff: Duration,
+    /// Upper limit of the delay between two attempts
+    pub max_backoff: Duration,
+    /// Longest time to wait for a rate limit reset. If the reset is further away, the request fails
+    pub max_rate_limit_wait: Duration,
+    /// Also retry POST, PUT, PATCH and DELETE requests that may have reached the api,
+    /// after a `5xx` status code, a timeout or an io error
+    pub retry_mutations: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            max_rate_limit_wait: Duration::from_secs(60),
+            retry_mutations: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Every request is done only once
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the time to sleep before the next attempt,
+    /// or None if the error should be returned to the caller.
+    pub(crate) fn delay(
+        &self,
+        attempt: u32,
+        method: Method,
+        error: &Error,
+        rate_limit: Option<&RateLimit>,
+    ) -> Option<Duration> {
+        let retryable = match retry_kind(error) {
+            RetryKind::Never => false,
+            RetryKind::Always => true,
+            RetryKind::Idempotent => method == Method::Get || self.retry_mutations,
+        };
+        if attempt >= self.max_attempts || !retryable {
+            return None;
+        }
+        if error.is_rate_limited()
+            && let Some(wait) = rate_limit.and_then(RateLimit::until_reset)
+        {
+            return (wait <= self.max_rate_limit_wait).then_some(wait);
+        }
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        Some(jitter(backoff))
+    }
+}
+
+enum RetryKind {
+    Never,
+    /// The request was rejected or never sent, so it was not carried out
+    Always,
+    /// The request may have been carried out
+    Idempotent,
+}
+
+fn retry_kind(error: &Error) -> RetryKind {
+    match error {
+        Error::Api { status: 429, .. } => RetryKind::Always,
+        Error::Api { status, .. } if *status >= 500 => RetryKind::Idempotent,
+        Error::IO(_) => RetryKind::Idempotent,
+        #[cfg(feature = "ureq")]
+        Error::Ureq(ureq::Error::ConnectionFailed) | Error::Ureq(ureq::Error::HostNotFound) => {
+            RetryKind::Always
+        }
+        #[cfg(feature = "ureq")]
+        Error::Ureq(ureq::Error::Io(_)) | Error::Ureq(ureq::Error::Timeout(_)) => {
+            RetryKind::Idempotent
+        }
+        _ => RetryKind::Never,
+    }
+}
+
+/// Random duration between half and the full duration
+fn jitter(duration: Duration) -> Duration {
+    let mut bytes = [0u8; 4];
+    let fraction = SystemRandom::new()
+        .fill(&mut bytes)
+        .map(|_| f64::from(u32::from_le_bytes(bytes)) / f64::from(u32::MAX))
+        .unwrap_or(1.0);
+    duration.mul_f64(0.5 + fraction / 2.0)
+}
+
+/// Request quota as reported by the api in the `X-Rate-Limit-*` headers of the last response
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLimit {
+    /// Number of requests allowed in the current window
+    pub limit: Option<u32>,
+    /// Number of requests left in the current window
+    pub remaining: u32,
+    /// Moment the current window ends and the quota is restored
+    pub reset: Option<DateTime<Utc>>,
+}
+
+impl RateLimit {
+    pub(crate) fn from_response(response: &Response) -> Option<Self> {
+        let header = |name: &str| response.header(name).map(str::trim);
+        let remaining = header(X_RATE_LIMIT_REMAINING)?.parse::<u32>().ok()?;
+        Some(Self {
+            limit: header(X_RATE_LIMIT_LIMIT).and_then(|s| s.parse::<u32>().ok()),
+            remaining,
+            reset: header(X_RATE_LIMIT_RESET)
+                .and_then(|s| s.parse::<i64>().ok())
+                .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0)),
+        })
+    }
+
+    /// Time left until the quota is restored
+    pub fn until_reset(&self) -> Option<Duration> {
+        self.reset
+            .map(|reset| (reset - Utc::now()).to_std().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    use chrono::Utc;
+
+    use super::{RateLimit, RetryPolicy};
+    use crate::Error;
+    use crate::transport::{Method, Response};
+
+    fn api_error(status: u16) -> Error {
+        Error::api("GET", "https://api.transip.nl/v6/vps", status, b"")
+    }
+
+    #[test]
+    fn rate_limit_from_headers() {
+        let reset = Utc::now().timestamp() + 30;
+        let response = Response::new(200)
+            .with_header("X-Rate-Limit-Limit", "1000")
+            .with_header("X-Rate-Limit-Remaining", "998")
+            .with_header("X-Rate-Limit-Reset", reset);
+
+        let rate_limit = RateLimit::from_response(&response).unwrap();
+        assert_eq!(rate_limit.limit, Some(1000));
+        assert_eq!(rate_limit.remaining, 998);
+        assert_eq!(rate_limit.reset.unwrap().timestamp(), reset);
+        assert!(rate_limit.until_reset().unwrap() <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn rate_limit_missing() {
+        assert!(RateLimit::from_response(&Response::new(200)).is_none());
+    }
+
+    #[test]
+    fn no_retry_on_client_error() {
+        let policy = RetryPolicy::default();
+        assert!(
+            policy
+                .delay(1, Method::Get, &api_error(404), None)
+                .is_none()
+        );
+        assert!(policy.delay(1, Method::Get, &Error::Token, None).is_none());
+    }
+
+    #[test]
+    fn no_retry_after_max_attempts() {
+        let policy = RetryPolicy::default();
+        assert!(
+            policy
+                .delay(1, Method::Get, &api_error(503), None)
+                .is_some()
+        );
+        assert!(
+            policy
+                .delay(3, Method::Get, &api_error(503), None)
+                .is_none()
+        );
+        assert!(
+            RetryPolicy::none()
+                .delay(1, Method::Get, &api_error(503), None)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            ..Default::default()
+        };
+        let first = policy.delay(1, Method::Get, &api_error(502), None).unwrap();
+        assert!(first >= Duration::from_millis(500) && first <= Duration::from_secs(1));
+        let third = policy.delay(3, Method::Get, &api_error(502), None).unwrap();
+        assert!(third >= Duration::from_secs(2) && third <= Duration::from_secs(4));
+        let capped = policy.delay(9, Method::Get, &api_error(502), None).unwrap();
+        assert!(capped <= Duration::from_secs(5));
+    }
+
+    #[test]
+    fn wait_for_rate_limit_reset() {
+        let policy = RetryPolicy::default();
+        let soon = RateLimit {
+            limit: None,
+            remaining: 0,
+            reset: Some(Utc::now() + Duration::from_secs(20)),
+        };
+        let delay = policy
+            .delay(1, Method::Get, &api_error(429), Some(&soon))
+            .unwrap();
+        assert!(delay > Duration::from_secs(18) && delay <= Duration::from_secs(20));
+
+        let later = RateLimit {
+            reset: Some(Utc::now() + Duration::from_secs(3600)),
+            ..soon
+        };
+        assert!(
+            policy
+                .delay(1, Method::Get, &api_error(429), Some(&later))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn no_retry_of_mutation_that_may_be_done() {
+        let policy = RetryPolicy::default();
+        for method in [Method::Post, Method::Put, Method::Patch, Method::Delete] {
+            assert!(policy.delay(1, method, &api_error(503), None).is_none());
+            assert!(policy.delay(1, method, &api_error(429), None).is_some());
+        }
+        let policy = RetryPolicy {
+            retry_mutations: true,
+            ..Default::default()
+        };
+        assert!(
+            policy
+                .delay(1, Method::Post, &api_error(503), None)
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::{Arc, Mutex};
+
+use super::{Method, Request, Response, Transport};
+use crate::Result;
+
+struct Route {
+    method: Method,
+    path: String,
+    response: Response,
+}
+
+impl Route {
+    /// The path of the request should end with the path of the route.
+    /// A route without a query matches requests with any query.
+    fn matches(&self, request: &Request) -> bool {
+        let path = request.path();
+        let without_query = path.split('?').next().unwrap_or(path);
+        self.method == request.method
+            && (path.ends_with(&self.path) || without_query.ends_with(&self.path))
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    routes: Vec<Route>,
+    requests: Vec<Request>,
+}
+
+/// Transport that answers requests with configured responses, without using the network.
+///
+/// Every request is recorded, so a test can check what the client has sent.
+/// Requests without a matching route get a `404` response.
+/// Clones share the routes and recorded requests.
+#[derive(Clone, Default)]
+pub struct MemoryTransport {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl MemoryTransport {
+    /// Respond to requests with this method and path.
+    /// A request matches if the path of its url ends with this path,
+    /// so the url prefix of the client does not matter.
+    /// The first matching route is used.
+    pub fn respond(self, method: Method, path: &str, response: Response) -> Self {
+        self.inner.lock().unwrap().routes.push(Route {
+            method,
+            path: path.to_owned(),
+            response,
+        });
+        self
+    }
+
+    /// All requests received so far
+    pub fn requests(&self) -> Vec<Request> {
+        self.inner.lock().unwrap().requests.clone()
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&self, request: Request) -> Result<Response> {
+        let mut inner = self.inner.lock().unwrap();
+        let response = inner
+            .routes
+            .iter()
+            .find(|route| route.matches(&request))
+            .map(|route| route.response.clone())
+            .unwrap_or_else(|| {
+                Response::json(
+                    404,
+                    format!(
+                        r#"{{"error":"No response for {} {}"}}"#,
+                        request.method,
+                        request.path()
+                    ),
+                )
+            });
+        inner.requests.push(request);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MemoryTransport;
+    use crate::transport::{Method, Request, Response, Transport};
+
+    #[test]
+    fn matching_route() {
+        let transport = MemoryTransport::default()
+            .respond(Method::Get, "/v6/vps", Response::json(200, "{}"))
+            .respond(Method::Delete, "/v6/vps", Response::new(204));
+
+        let response = transport
+            .send(Request::new(
+                Method::Delete,
+                "https://api.transip.nl/v6/vps",
+            ))
+            .unwrap();
+        assert_eq!(response.status, 204);
+
+        let response = transport
+            .send(Request::new(
+                Method::Get,
+                "https://api.transip.nl/v6/vps?page=2",
+            ))
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[test]
+    fn route_suffix() {
+        let transport =
+            MemoryTransport::default().respond(Method::Get, "/api-test", Response::new(200));
+        let response = transport
+            .send(Request::new(
+                Method::Get,
+                "https://api.transip.nl/v6/api-test",
+            ))
+            .unwrap();
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn no_route() {
+        let transport = MemoryTransport::default();
+        let response = transport
+            .send(Request::new(Method::Post, "https://api.transip.nl/v6/vps").body(vec![1]))
+            .unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(transport.requests()[0].body, Some(vec![1]));
+    }
+}
@@ -0,0 +1,238 @@
+//! The [`Client`](crate::Client) sends every request through a [`Transport`].
+//!
+//! By default [`UreqTransport`] is used, which is available with the `ureq` feature.
+//! Implement [`Transport`] to use another http stack,
+//! or use [`MemoryTransport`] to test code without opening sockets.
+//! [`RecordingTransport`] writes the requests of a session to a fixture file,
+//! that [`ReplayTransport`] serves back in tests.
+//!
+//! # Example
+//!
+//! ```
+//! use transip::{api::general::GeneralApi, Client};
+//! use transip::transport::{MemoryTransport, Method, Response};
+//!
+//! let transport = MemoryTransport::default()
+//!     .respond(Method::Get, "/api-test", Response::json(200, r#"{"ping":"pong"}"#));
+//! let client =
+//!     Client::test_with_transport("https://api.transip.nl/v6".into(), transport.clone());
+//!
+//! assert_eq!(client.api_test().unwrap(), "pong");
+//! assert_eq!(transport.requests().len(), 1);
+//! ```
+
+use std::fmt::Display;
+
+use serde::{Serialize, de::DeserializeOwned};
+use strum::{Display, EnumString};
+
+use crate::{Error, Result};
+
+pub use memory::MemoryTransport;
+pub use record::{Exchange, RecordingTransport, ReplayTransport};
+#[cfg(feature = "ureq")]
+pub use ureq::UreqTransport;
+
+mod memory;
+mod record;
+#[cfg(feature = "ureq")]
+mod ureq;
+
+const CONTENT_TYPE: &str = "Content-Type";
+const APPLICATION_JSON: &str = "application/json";
+
+/// Sends a single http request and returns the response.
+///
+/// A transport should only return an error if no response was received.
+/// Responses with a 4xx or 5xx status code are handled by the client.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: Request) -> Result<Response>;
+}
+
+impl<T> Transport for Box<T>
+where
+    T: Transport + ?Sized,
+{
+    fn send(&self, request: Request) -> Result<Response> {
+        self.as_ref().send(request)
+    }
+}
+
+impl<T> Transport for std::sync::Arc<T>
+where
+    T: Transport + ?Sized,
+{
+    fn send(&self, request: Request) -> Result<Response> {
+        self.as_ref().send(request)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq)]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl Request {
+    pub fn new<S: Into<String>>(method: Method, url: S) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: vec![],
+            body: None,
+        }
+    }
+
+    pub fn header<K: Display, V: Display>(mut self, name: K, value: V) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Serializes the object as json body
+    pub fn json<T: Serialize>(self, object: &T) -> Result<Self> {
+        let body = serde_json::to_vec(object)?;
+        Ok(self.body(body).header(CONTENT_TYPE, APPLICATION_JSON))
+    }
+
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Path and query of the url, for example `/v6/domains?page=2`
+    pub fn path(&self) -> &str {
+        self.url
+            .split_once("://")
+            .map(|(_, rest)| rest.find('/').map(|index| &rest[index..]).unwrap_or("/"))
+            .unwrap_or(self.url.as_str())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    /// Response with a json body
+    pub fn json<B: Into<Vec<u8>>>(status: u16, body: B) -> Self {
+        Self::new(status)
+            .with_header(CONTENT_TYPE, APPLICATION_JSON)
+            .with_body(body)
+    }
+
+    pub fn with_header<K: Display, V: Display>(mut self, name: K, value: V) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Value of the first header with this name, compared case insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn read_json<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body).map_err(Error::from)
+    }
+
+    /// Turns 4xx and 5xx responses into [`Error::Api`]
+    pub(crate) fn error_for_status(self, request: &Request) -> Result<Self> {
+        if self.status >= 400 {
+            Err(Error::api(
+                request.method.to_string(),
+                request.url.as_str(),
+                self.status,
+                &self.body,
+            ))
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Method, Request, Response};
+
+    #[test]
+    fn request_path() {
+        assert_eq!(
+            Request::new(
+                Method::Get,
+                "https://api.transip.nl/v6/domains?include=nameservers"
+            )
+            .path(),
+            "/v6/domains?include=nameservers"
+        );
+        assert_eq!(Request::new(Method::Get, "http://127.0.0.1:80").path(), "/");
+    }
+
+    #[test]
+    fn request_json() {
+        let request = Request::new(Method::Post, "http://localhost/vps")
+            .json(&serde_json::json!({"action": "start"}))
+            .unwrap();
+        assert_eq!(request.body.unwrap(), br#"{"action":"start"}"#);
+        assert_eq!(
+            request.headers,
+            vec![("Content-Type".to_owned(), "application/json".to_owned())]
+        );
+    }
+
+    #[test]
+    fn method_display() {
+        assert_eq!(Method::Delete.to_string(), "DELETE");
+        assert_eq!("PATCH".parse::<Method>().unwrap(), Method::Patch);
+    }
+
+    #[test]
+    fn response_header() {
+        let response = Response::new(200).with_header("X-Rate-Limit-Remaining", "10");
+        assert_eq!(response.header("x-rate-limit-remaining"), Some("10"));
+        assert_eq!(response.header("X-Rate-Limit-Reset"), None);
+    }
+
+    #[test]
+    fn response_error_for_status() {
+        let request = Request::new(Method::Get, "http://localhost/domains/example.com");
+        let error = Response::json(404, r#"{"error":"Not found"}"#)
+            .error_for_status(&request)
+            .unwrap_err();
+        assert!(error.is_not_found());
+        assert!(Response::new(204).error_for_status(&request).is_ok());
+    }
+}
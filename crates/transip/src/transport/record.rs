@@ -0,0 +1,235 @@
+use std::{
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{Request, Response, Transport};
+use crate::fs::FileSystem;
+use crate::{Error, Result, error::ResultExt};
+
+const AUTH: &str = "/auth";
+
+/// One request with the response it received, as stored in a fixture file
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Exchange {
+    pub method: String,
+    /// Path and query of the request url
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<Value>,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+}
+
+/// Json bodies are stored as json, so fixtures stay readable. Other bodies are stored as string.
+fn to_value(body: &[u8]) -> Option<Value> {
+    if body.is_empty() {
+        None
+    } else {
+        serde_json::from_slice(body)
+            .ok()
+            .or_else(|| Some(Value::String(String::from_utf8_lossy(body).into_owned())))
+    }
+}
+
+fn to_body(value: &Option<Value>) -> Vec<u8> {
+    match value {
+        None => vec![],
+        Some(Value::String(s)) => s.as_bytes().to_vec(),
+        Some(value) => value.to_string().into_bytes(),
+    }
+}
+
+impl Exchange {
+    fn new(request: &Request, response: &Response) -> Self {
+        Self {
+            method: request.method.to_string(),
+            path: request.path().to_owned(),
+            request: request.body.as_deref().and_then(to_value),
+            status: response.status,
+            headers: response.headers.clone(),
+            response: to_value(&response.body),
+        }
+    }
+
+    fn matches(&self, request: &Request) -> bool {
+        self.method == request.method.to_string() && self.path == request.path()
+    }
+
+    fn response(&self) -> Response {
+        Response {
+            status: self.status,
+            headers: self.headers.clone(),
+            body: to_body(&self.response),
+        }
+    }
+}
+
+/// Transport that passes requests on to another transport
+/// and writes every request with its response to a fixture file.
+///
+/// The file is rewritten after every request, so it is complete even if the process is killed.
+/// Request headers are not recorded and token requests are skipped,
+/// so no credentials end up in the fixture.
+/// Use [`ReplayTransport`] to serve the recorded responses.
+pub struct RecordingTransport<T> {
+    inner: T,
+    path: PathBuf,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl<T> RecordingTransport<T> {
+    pub fn new<P: AsRef<Path>>(inner: T, path: P) -> Self {
+        Self {
+            inner,
+            path: path.as_ref().to_path_buf(),
+            exchanges: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl<T> Transport for RecordingTransport<T>
+where
+    T: Transport,
+{
+    fn send(&self, request: Request) -> Result<Response> {
+        let response = self.inner.send(request.clone())?;
+        if !request.path().ends_with(AUTH) {
+            let mut exchanges = self.exchanges.lock().unwrap();
+            exchanges.push(Exchange::new(&request, &response));
+            let json = serde_json::to_vec_pretty(&*exchanges)?;
+            std::fs::write(&self.path, json)?;
+        }
+        Ok(response)
+    }
+}
+
+/// Transport that serves the responses from a fixture file created by [`RecordingTransport`].
+///
+/// Each request gets the first recorded response with the same method, path and query
+/// that was not served before. If there is no such response, an error is returned.
+/// This makes tests deterministic and independent of the network.
+pub struct ReplayTransport {
+    exchanges: Mutex<Vec<(Exchange, bool)>>,
+}
+
+impl ReplayTransport {
+    pub fn try_from_reader<R: Read>(reader: R) -> Result<Self> {
+        serde_json::from_reader::<_, Vec<Exchange>>(BufReader::new(reader))
+            .err_into()
+            .map(Self::from)
+    }
+
+    pub fn try_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        path.reader().and_then(Self::try_from_reader)
+    }
+}
+
+impl From<Vec<Exchange>> for ReplayTransport {
+    fn from(exchanges: Vec<Exchange>) -> Self {
+        Self {
+            exchanges: Mutex::new(exchanges.into_iter().map(|e| (e, false)).collect()),
+        }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: Request) -> Result<Response> {
+        let mut exchanges = self.exchanges.lock().unwrap();
+        exchanges
+            .iter_mut()
+            .find(|(exchange, served)| !served && exchange.matches(&request))
+            .map(|(exchange, served)| {
+                *served = true;
+                exchange.response()
+            })
+            .ok_or_else(|| {
+                Error::Replay(format!(
+                    "No recorded response for {} {}",
+                    request.method,
+                    request.path()
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RecordingTransport, ReplayTransport};
+    use crate::transport::{MemoryTransport, Method, Request, Response, Transport};
+
+    fn memory() -> MemoryTransport {
+        MemoryTransport::default()
+            .respond(
+                Method::Get,
+                "/v6/api-test",
+                Response::json(200, r#"{"ping":"pong"}"#),
+            )
+            .respond(
+                Method::Post,
+                "/v6/auth",
+                Response::json(201, r#"{"token":"secret"}"#),
+            )
+            .respond(Method::Delete, "/v6/vps/vps1", Response::new(204))
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let path = std::env::temp_dir().join(format!("transip-record-{}.json", std::process::id()));
+        let recorder = RecordingTransport::new(memory(), &path);
+        recorder
+            .send(Request::new(Method::Post, "https://api.transip.nl/v6/auth").body(b"{}".to_vec()))
+            .unwrap();
+        recorder
+            .send(Request::new(
+                Method::Get,
+                "https://api.transip.nl/v6/api-test",
+            ))
+            .unwrap();
+        recorder
+            .send(
+                Request::new(Method::Delete, "https://api.transip.nl/v6/vps/vps1")
+                    .json(&serde_json::json!({"id": 1}))
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let fixture = std::fs::read_to_string(&path).unwrap();
+        assert!(!fixture.contains("secret"));
+
+        let replay = ReplayTransport::try_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let response = replay
+            .send(Request::new(Method::Get, "http://localhost/v6/api-test"))
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, br#"{"ping":"pong"}"#);
+        assert_eq!(response.header("content-type"), Some("application/json"));
+
+        let response = replay
+            .send(Request::new(Method::Delete, "http://localhost/v6/vps/vps1"))
+            .unwrap();
+        assert_eq!(response.status, 204);
+        assert!(response.body.is_empty());
+    }
+
+    #[test]
+    fn replay_only_once() {
+        let replay = ReplayTransport::try_from_reader(
+            r#"[{"method":"GET","path":"/v6/api-test","status":200,"response":{"ping":"pong"}}]"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let request = Request::new(Method::Get, "https://api.transip.nl/v6/api-test");
+        assert!(replay.send(request.clone()).is_ok());
+        assert!(replay.send(request).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use ::ureq::config::{ConfigBuilder, IpFamily as UreqIpFamily};
+use ::ureq::typestate::AgentScope;
+use ::ureq::{Agent, http};
+
+use super::{Request, Response, Transport};
+use crate::{IpFamily, Result, error::ResultExt};
+
+/// Transport using a blocking [`ureq::Agent`](::ureq::Agent)
+pub struct UreqTransport {
+    agent: Agent,
+}
+
+impl UreqTransport {
+    /// Agent configuration used by the client.
+    /// Status codes 4xx and 5xx are not turned into errors, so the error json in the body can be read.
+    pub fn config_builder(ip_family: IpFamily) -> ConfigBuilder<AgentScope> {
+        Agent::config_builder()
+            .http_status_as_error(false)
+            .ip_family(match ip_family {
+                IpFamily::Any => UreqIpFamily::Any,
+                IpFamily::Ipv4Only => UreqIpFamily::Ipv4Only,
+                IpFamily::Ipv6Only => UreqIpFamily::Ipv6Only,
+            })
+    }
+}
+
+impl From<Agent> for UreqTransport {
+    fn from(agent: Agent) -> Self {
+        Self { agent }
+    }
+}
+
+impl Transport for UreqTransport {
+    fn send(&self, request: Request) -> Result<Response> {
+        let mut builder = http::Request::builder()
+            .method(request.method.to_string().as_str())
+            .uri(request.url.as_str());
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        // `Agent::run` sends the body of any method, including DELETE
+        let response = match request.body {
+            Some(body) => self
+                .agent
+                .run(builder.body(body).map_err(::ureq::Error::from)?),
+            None => self
+                .agent
+                .run(builder.body(()).map_err(::ureq::Error::from)?),
+        }?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.to_string(), value.to_owned()))
+            })
+            .collect();
+        let body = response.into_body().read_to_vec().err_into()?;
+        Ok(Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}